@@ -72,7 +72,7 @@ fn parse_line(line: &str) -> (Policy, String) {
 
 pub(crate) fn day02() {
     let input = std::fs::read_to_string("data/day02.txt").unwrap();
-    let tries: Vec<(Policy, String)> = input.lines().map(|line| parse_line(&line)).collect();
+    let tries: Vec<(Policy, String)> = input.lines().map(parse_line).collect();
 
     let valid_count = tries
         .iter()
@@ -1,76 +1,349 @@
 use regex::Regex;
-use std::collections::HashMap;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fmt::Display;
+use std::path::Path;
+use std::str::FromStr;
+
+// The puzzle's own rules.
+const DEFAULT_SCHEMA: &str = include_str!("../data/day04_schema.toml");
+
+// What to do with a field that the schema doesn't mention.
+#[derive(Copy, Clone, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum UnknownFields {
+    Allow,
+    #[default]
+    Reject,
+}
+
+// Expects a number followed by a unit, like "150cm".
+#[derive(Deserialize)]
+struct UnitRange {
+    unit: String,
+    min: u32,
+    max: u32,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Rule {
+    #[default]
+    Any,
+    IntRange {
+        min: u32,
+        max: u32,
+    },
+    UnitRange {
+        units: Vec<UnitRange>,
+    },
+    Regex {
+        #[serde(deserialize_with = "deserialize_regex")]
+        pattern: Regex,
+    },
+    OneOf {
+        values: Vec<String>,
+    },
+}
+
+fn deserialize_regex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Regex, D::Error> {
+    let pattern = String::deserialize(deserializer)?;
+    Regex::new(&pattern).map_err(serde::de::Error::custom)
+}
+
+impl Rule {
+    fn validate(&self, value: &str) -> bool {
+        match self {
+            Self::Any => true,
+            Self::IntRange { min, max } => value
+                .parse::<u32>()
+                .is_ok_and(|n| (*min..=*max).contains(&n)),
+            Self::UnitRange { units } => units.iter().any(|range| {
+                value
+                    .strip_suffix(range.unit.as_str())
+                    .and_then(|number| number.parse::<u32>().ok())
+                    .is_some_and(|n| (range.min..=range.max).contains(&n))
+            }),
+            Self::Regex { pattern } => pattern.is_match(value),
+            Self::OneOf { values } => values.iter().any(|v| v == value),
+        }
+    }
+}
+
+impl Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Any => write!(f, "anything"),
+            Self::IntRange { min, max } => write!(f, "integer from {} to {}", min, max),
+            Self::UnitRange { units } => {
+                let ranges: Vec<String> = units
+                    .iter()
+                    .map(|range| format!("{}-{}{}", range.min, range.max, range.unit))
+                    .collect();
+                write!(f, "{}", ranges.join(" or "))
+            }
+            Self::Regex { pattern } => write!(f, "matches {}", pattern),
+            Self::OneOf { values } => write!(f, "one of {}", values.join(", ")),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct FieldSpec {
+    #[serde(default)]
+    required: bool,
+
+    #[serde(default)]
+    rule: Rule,
+}
+
+#[derive(Deserialize)]
+struct Schema {
+    fields: BTreeMap<String, FieldSpec>,
+
+    #[serde(default)]
+    unknown_fields: UnknownFields,
+}
+
+#[derive(Debug)]
+enum SchemaError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Toml(toml::de::Error),
+    UnknownFormat,
+}
+
+impl Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{}", err),
+            Self::Json(err) => write!(f, "{}", err),
+            Self::Toml(err) => write!(f, "{}", err),
+            Self::UnknownFormat => write!(f, "schema must be a .toml or .json file"),
+        }
+    }
+}
+
+impl From<std::io::Error> for SchemaError {
+    fn from(err: std::io::Error) -> Self {
+        SchemaError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for SchemaError {
+    fn from(err: serde_json::Error) -> Self {
+        SchemaError::Json(err)
+    }
+}
+
+impl From<toml::de::Error> for SchemaError {
+    fn from(err: toml::de::Error) -> Self {
+        SchemaError::Toml(err)
+    }
+}
+
+impl Default for Schema {
+    fn default() -> Self {
+        toml::from_str(DEFAULT_SCHEMA).expect("bad default schema")
+    }
+}
+
+impl Schema {
+    // The format is chosen by file extension: either TOML or JSON.
+    fn load(path: &Path) -> Result<Self, SchemaError> {
+        let text = std::fs::read_to_string(path)?;
+        let schema = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => toml::from_str(&text)?,
+            Some("json") => serde_json::from_str(&text)?,
+            _ => return Err(SchemaError::UnknownFormat),
+        };
+        Ok(schema)
+    }
+
+    fn required_fields(&self) -> impl Iterator<Item = &str> {
+        self.fields
+            .iter()
+            .filter(|(_key, spec)| spec.required)
+            .map(|(key, _spec)| key.as_str())
+    }
+
+    // On failure, describes what's wrong with the field.
+    fn validate_field(&self, key: &str, value: &str) -> Result<(), String> {
+        match self.fields.get(key) {
+            Some(spec) if spec.rule.validate(value) => Ok(()),
+            Some(spec) => Err(format!("expected {}", spec.rule)),
+            None => match self.unknown_fields {
+                UnknownFields::Allow => Ok(()),
+                UnknownFields::Reject => Err("unknown field not allowed".to_owned()),
+            },
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct InvalidField {
+    key: String,
+    value: String,
+    problem: String,
+}
+
+#[derive(Default, Serialize)]
+struct ValidationReport {
+    missing: Vec<String>,
+    invalid: Vec<InvalidField>,
+    duplicated: Vec<String>,
+    malformed: Vec<String>,
+}
+
+impl ValidationReport {
+    fn is_valid(&self) -> bool {
+        self.missing.is_empty()
+            && self.invalid.is_empty()
+            && self.duplicated.is_empty()
+            && self.malformed.is_empty()
+    }
+}
+
+impl Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for key in &self.missing {
+            writeln!(f, "missing field {}", key)?;
+        }
+        for field in &self.invalid {
+            writeln!(
+                f,
+                "invalid field {}:{}, {}",
+                field.key, field.value, field.problem
+            )?;
+        }
+        for key in &self.duplicated {
+            writeln!(f, "duplicated field {}", key)?;
+        }
+        for pair in &self.malformed {
+            writeln!(f, "malformed entry {:?}", pair)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Copy, Clone)]
+pub(crate) enum ReportFormat {
+    Text,
+    Json,
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("unknown report format {}", s)),
+        }
+    }
+}
 
 #[derive(Default)]
 struct Passport {
-    fields: HashMap<String, String>,
+    fields: BTreeMap<String, String>,
+
+    // Keys that appeared more than once: we keep the last value.
+    duplicated: Vec<String>,
+
+    // Entries that weren't of the form "key:value".
+    malformed: Vec<String>,
 }
 
 impl Passport {
+    fn add_pair(&mut self, pair: &str) {
+        match pair.split_once(':') {
+            Some((key, value)) if !key.is_empty() => {
+                self.add_entry(key.to_owned(), value.to_owned())
+            }
+            _ => self.malformed.push(pair.to_owned()),
+        }
+    }
+
     fn add_entry(&mut self, key: String, value: String) {
+        if self.fields.contains_key(&key) {
+            self.duplicated.push(key.clone());
+        }
         self.fields.insert(key, value);
     }
 
-    fn is_fully_valid(&self) -> bool {
-        if !self.has_required_fields() {
-            return false;
-        }
+    fn validate(&self, schema: &Schema) -> ValidationReport {
+        let missing = schema
+            .required_fields()
+            .filter(|&key| !self.fields.contains_key(key))
+            .map(|key| key.to_owned())
+            .collect();
 
-        self.fields
+        let invalid = self
+            .fields
             .iter()
-            .all(|(key, value)| Self::validate_field(key, value))
+            .filter_map(|(key, value)| {
+                let problem = schema.validate_field(key, value).err()?;
+                let field = InvalidField {
+                    key: key.clone(),
+                    value: value.clone(),
+                    problem,
+                };
+                Some(field)
+            })
+            .collect();
+
+        ValidationReport {
+            missing,
+            invalid,
+            duplicated: self.duplicated.clone(),
+            malformed: self.malformed.clone(),
+        }
     }
 
-    fn has_required_fields(&self) -> bool {
-        let required = &["byr", "iyr", "eyr", "hgt", "hcl", "ecl", "pid"];
-        required.iter().all(|&key| self.fields.contains_key(key))
+    fn has_required_fields(&self, schema: &Schema) -> bool {
+        schema
+            .required_fields()
+            .all(|key| self.fields.contains_key(key))
     }
+}
 
-    fn validate_field(key: &str, value: &str) -> bool {
-        match key {
-            "byr" => value
-                .parse::<u32>()
-                .map_or(false, |y| 1920 <= y && y <= 2002),
-            "iyr" => value
-                .parse::<u32>()
-                .map_or(false, |y| 2010 <= y && y <= 2020),
-            "eyr" => value
-                .parse::<u32>()
-                .map_or(false, |y| 2020 <= y && y <= 2030),
-            "hgt" => {
-                if let Some(cm) = value.strip_suffix("cm") {
-                    cm.parse::<u32>().map_or(false, |cm| 150 <= cm && cm <= 193)
-                } else if let Some(inches) = value.strip_suffix("in") {
-                    inches
-                        .parse::<u32>()
-                        .map_or(false, |inches| 59 <= inches && inches <= 76)
-                } else {
-                    false
-                }
-            }
-            "hcl" => {
-                lazy_static! {
-                    static ref HCL: Regex = Regex::new(r"^#[0-9a-f]{6}$").unwrap();
-                }
-                HCL.is_match(value)
-            }
-            "ecl" => {
-                let valid = &["amb", "blu", "brn", "gry", "grn", "hzl", "oth"];
-                valid.contains(&value)
-            }
-            "pid" => {
-                lazy_static! {
-                    static ref PID: Regex = Regex::new(r"^\d{9}$").unwrap();
-                }
-                PID.is_match(value)
+#[derive(Serialize)]
+struct PassportReport<'a> {
+    passport: usize,
+
+    #[serde(flatten)]
+    report: &'a ValidationReport,
+}
+
+fn print_reports(reports: &[ValidationReport], format: ReportFormat) {
+    let failures = reports
+        .iter()
+        .enumerate()
+        .filter(|(_index, report)| !report.is_valid());
+    match format {
+        ReportFormat::Text => {
+            for (index, report) in failures {
+                println!("Passport {}:", index);
+                print!("{}", report);
             }
-            "cid" => true,
-            _ => panic!("unexpected field"),
+        }
+        ReportFormat::Json => {
+            let failures: Vec<PassportReport> = failures
+                .map(|(passport, report)| PassportReport { passport, report })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&failures).unwrap());
         }
     }
 }
 
-pub(crate) fn day04() {
+pub(crate) fn day04(schema: Option<&Path>, report: Option<ReportFormat>) {
+    let schema = match schema {
+        Some(path) => Schema::load(path).unwrap_or_else(|err| panic!("bad schema: {}", err)),
+        None => Schema::default(),
+    };
+
     let input = std::fs::read_to_string("data/day04.txt").unwrap();
 
     let mut passports: Vec<Passport> = vec![];
@@ -82,19 +355,23 @@ pub(crate) fn day04() {
             continue;
         }
 
-        let pairs = line.split(' ');
-        for pair in pairs {
-            let mut key_value = pair.split(':');
-            let key = key_value.next().expect("no key");
-            let value = key_value.next().expect("no value");
-            passport.add_entry(key.to_owned(), value.to_owned());
+        for pair in line.split_whitespace() {
+            passport.add_pair(pair);
         }
     }
     passports.push(passport);
 
-    let valid = passports.iter().filter(|p| p.has_required_fields()).count();
+    let valid = passports
+        .iter()
+        .filter(|p| p.has_required_fields(&schema))
+        .count();
     println!("Part one answer is {}", valid);
 
-    let valid = passports.iter().filter(|p| p.is_fully_valid()).count();
+    let reports: Vec<ValidationReport> = passports.iter().map(|p| p.validate(&schema)).collect();
+    let valid = reports.iter().filter(|report| report.is_valid()).count();
     println!("Part two answer is {}", valid);
+
+    if let Some(format) = report {
+        print_reports(&reports, format);
+    }
 }
@@ -1,17 +1,211 @@
 use std::collections::HashSet;
+use std::fmt;
+use std::fmt::Display;
 
-// It's just a binary number with a complicated description...
-fn decode_pass(text: &str) -> u32 {
-    text.chars().fold(0, |value, c| match c {
-        'F' | 'L' => 2 * value,
-        'B' | 'R' => 1 + 2 * value,
-        _ => panic!("bad pass"),
-    })
+#[derive(Copy, Clone)]
+struct Seat {
+    row: u32,
+    column: u32,
 }
 
-pub(crate) fn day05() {
+#[derive(Debug)]
+enum PassError {
+    Length { expected: usize, actual: usize },
+    Letter { position: usize, letter: char },
+}
+
+impl Display for PassError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Length { expected, actual } => {
+                write!(f, "expected {} letters, found {}", expected, actual)
+            }
+            Self::Letter { position, letter } => {
+                write!(f, "unexpected letter {:?} at position {}", letter, position)
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+enum RangeError {
+    Size { rows: u32, columns: u32 },
+    Id { id: u32, seats: u32 },
+    Row { row: u32, rows: u32 },
+    Column { column: u32, columns: u32 },
+}
+
+impl Display for RangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Size { rows, columns } => write!(
+                f,
+                "can't have a plane with {} rows and {} columns",
+                rows, columns
+            ),
+            Self::Id { id, seats } => write!(f, "seat {} isn't one of the {} seats", id, seats),
+            Self::Row { row, rows } => write!(f, "row {} isn't one of the {} rows", row, rows),
+            Self::Column { column, columns } => {
+                write!(f, "column {} isn't one of the {} columns", column, columns)
+            }
+        }
+    }
+}
+
+// Seat IDs are u32s, so a plane can't have more seats than that.
+const MAX_SEAT_BITS: u32 = 31;
+
+// The plane has 2^row_bits rows and 2^column_bits columns.
+struct Plane {
+    row_bits: u32,
+    column_bits: u32,
+}
+
+impl Default for Plane {
+    fn default() -> Self {
+        Self::new(7, 3)
+    }
+}
+
+impl Plane {
+    fn new(row_bits: u32, column_bits: u32) -> Self {
+        Self {
+            row_bits,
+            column_bits,
+        }
+    }
+
+    // Rows and columns have to be powers of two, since each letter of a pass halves them.
+    fn with_size(rows: u32, columns: u32) -> Result<Self, RangeError> {
+        let plane = Self::new(rows.trailing_zeros(), columns.trailing_zeros());
+        if !rows.is_power_of_two()
+            || !columns.is_power_of_two()
+            || plane.row_bits + plane.column_bits > MAX_SEAT_BITS
+        {
+            return Err(RangeError::Size { rows, columns });
+        }
+        Ok(plane)
+    }
+
+    fn seats(&self) -> u32 {
+        self.rows() * self.columns()
+    }
+
+    fn rows(&self) -> u32 {
+        1 << self.row_bits
+    }
+
+    fn columns(&self) -> u32 {
+        1 << self.column_bits
+    }
+
+    fn seat_id(&self, seat: Seat) -> u32 {
+        (seat.row << self.column_bits) | seat.column
+    }
+
+    fn seat(&self, id: u32) -> Result<Seat, RangeError> {
+        if id >= self.seats() {
+            return Err(RangeError::Id {
+                id,
+                seats: self.seats(),
+            });
+        }
+        let row = id >> self.column_bits;
+        let column = id & (self.columns() - 1);
+        Ok(Seat { row, column })
+    }
+
+    // It's just a binary number with a complicated description...
+    fn decode(&self, pass: &str) -> Result<Seat, PassError> {
+        let expected = (self.row_bits + self.column_bits) as usize;
+        let actual = pass.chars().count();
+        if actual != expected {
+            return Err(PassError::Length { expected, actual });
+        }
+
+        let mut seat = Seat { row: 0, column: 0 };
+        for (position, letter) in pass.chars().enumerate() {
+            let in_row = position < self.row_bits as usize;
+            match (in_row, letter) {
+                (true, 'F') => seat.row *= 2,
+                (true, 'B') => seat.row = 1 + 2 * seat.row,
+                (false, 'L') => seat.column *= 2,
+                (false, 'R') => seat.column = 1 + 2 * seat.column,
+                _ => return Err(PassError::Letter { position, letter }),
+            }
+        }
+        Ok(seat)
+    }
+
+    fn encode(&self, seat: Seat) -> Result<String, RangeError> {
+        if seat.row >= self.rows() {
+            return Err(RangeError::Row {
+                row: seat.row,
+                rows: self.rows(),
+            });
+        }
+        if seat.column >= self.columns() {
+            return Err(RangeError::Column {
+                column: seat.column,
+                columns: self.columns(),
+            });
+        }
+        let bits = |value: u32, count: u32, zero: char, one: char| {
+            (0..count)
+                .rev()
+                .map(move |bit| if value & (1 << bit) == 0 { zero } else { one })
+        };
+        let pass = bits(seat.row, self.row_bits, 'F', 'B')
+            .chain(bits(seat.column, self.column_bits, 'L', 'R'))
+            .collect();
+        Ok(pass)
+    }
+
+    // One line per row: '#' for occupied seats, '.' for empty seats and 'O' for ours.
+    fn seat_map(&self, occupied: &HashSet<u32>, mine: u32) -> String {
+        let mut map = String::new();
+        for row in 0..self.rows() {
+            map.push_str(&format!("{:4} ", row));
+            for column in 0..self.columns() {
+                let id = self.seat_id(Seat { row, column });
+                let c = if id == mine {
+                    'O'
+                } else if occupied.contains(&id) {
+                    '#'
+                } else {
+                    '.'
+                };
+                map.push(c);
+            }
+            map.push('\n');
+        }
+        map
+    }
+}
+
+pub(crate) fn day05(seat_map: bool, rows: Option<u32>, columns: Option<u32>) {
     let input = std::fs::read_to_string("data/day05.txt").unwrap();
-    let seat_ids: HashSet<u32> = input.lines().map(decode_pass).collect();
+    let default = Plane::default();
+    let plane = match Plane::with_size(
+        rows.unwrap_or_else(|| default.rows()),
+        columns.unwrap_or_else(|| default.columns()),
+    ) {
+        Ok(plane) => plane,
+        Err(err) => {
+            println!("Bad plane: {}", err);
+            return;
+        }
+    };
+    let mut seat_ids = HashSet::new();
+    for (index, line) in input.lines().enumerate() {
+        match plane.decode(line) {
+            Ok(seat) => seat_ids.insert(plane.seat_id(seat)),
+            Err(err) => {
+                println!("Bad pass on line {}: {}", index + 1, err);
+                return;
+            }
+        };
+    }
 
     let &max_id = seat_ids.iter().max().unwrap();
     println!("Part one answer is {}", max_id);
@@ -19,4 +213,15 @@ pub(crate) fn day05() {
     let &min_id = seat_ids.iter().min().unwrap();
     let my_seat = (min_id..max_id).find(|id| !seat_ids.contains(id)).unwrap();
     println!("Part two answer is {}", my_seat);
+
+    if seat_map {
+        let seat = plane.seat(my_seat).unwrap();
+        println!(
+            "Our pass is {} (row {}, column {})",
+            plane.encode(seat).unwrap(),
+            seat.row,
+            seat.column
+        );
+        print!("{}", plane.seat_map(&seat_ids, my_seat));
+    }
 }
@@ -1,16 +1,80 @@
-use std::collections::HashSet;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::iter::FromIterator;
 use std::str::FromStr;
 
+const QUESTIONS: &str = "abcdefghijklmnopqrstuvwxyz";
+
+// Questions a-z are bits in a mask, anything else falls back to a set.
+#[derive(Clone, Default)]
+struct Answers {
+    letters: u32,
+    others: BTreeSet<char>,
+}
+
+impl Answers {
+    fn insert(&mut self, c: char) {
+        if c.is_ascii_lowercase() {
+            self.letters |= 1 << (c as u8 - b'a');
+        } else {
+            self.others.insert(c);
+        }
+    }
+
+    fn contains(&self, c: char) -> bool {
+        if c.is_ascii_lowercase() {
+            self.letters & (1 << (c as u8 - b'a')) != 0
+        } else {
+            self.others.contains(&c)
+        }
+    }
+
+    fn union(&self, other: &Self) -> Self {
+        Self {
+            letters: self.letters | other.letters,
+            others: self.others.union(&other.others).cloned().collect(),
+        }
+    }
+
+    fn intersection(&self, other: &Self) -> Self {
+        Self {
+            letters: self.letters & other.letters,
+            others: self.others.intersection(&other.others).cloned().collect(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.letters.count_ones() as usize + self.others.len()
+    }
+
+    fn iter(&self) -> impl Iterator<Item = char> + '_ {
+        QUESTIONS
+            .chars()
+            .filter(move |&c| self.contains(c))
+            .chain(self.others.iter().cloned())
+    }
+}
+
+impl FromIterator<char> for Answers {
+    fn from_iter<I: IntoIterator<Item = char>>(iter: I) -> Self {
+        let mut answers = Self::default();
+        for c in iter {
+            answers.insert(c);
+        }
+        answers
+    }
+}
+
 #[derive(Default)]
 struct Person {
-    yes: HashSet<char>,
+    yes: Answers,
 }
 
 impl FromStr for Person {
     type Err = ();
 
     fn from_str(s: &str) -> Result<Person, Self::Err> {
-        let yes: HashSet<_> = s.chars().collect();
+        let yes: Answers = s.chars().collect();
         let person = Self { yes };
         Ok(person)
     }
@@ -26,24 +90,75 @@ impl Group {
         self.people.push(person)
     }
 
+    fn any_yes(&self) -> Answers {
+        self.people
+            .iter()
+            .fold(Answers::default(), |yes, person| yes.union(&person.yes))
+    }
+
     fn any_yes_count(&self) -> usize {
-        let mut yes: HashSet<char> = HashSet::new();
-        for person in &self.people {
-            yes.extend(person.yes.iter());
-        }
-        yes.len()
+        self.any_yes().len()
     }
 
+    // An empty group has no questions that everyone answered.
     fn all_yes_count(&self) -> usize {
-        let mut yes: HashSet<char> = self.people[0].yes.clone();
-        for person in &self.people[1..] {
-            yes = yes.intersection(&person.yes).cloned().collect();
+        let mut people = self.people.iter();
+        let first = match people.next() {
+            Some(person) => person.yes.clone(),
+            None => return 0,
+        };
+        people
+            .fold(first, |yes, person| yes.intersection(&person.yes))
+            .len()
+    }
+
+    // How many people answered yes to each question.
+    fn frequencies(&self) -> BTreeMap<char, usize> {
+        let mut frequencies = BTreeMap::new();
+        for person in &self.people {
+            for c in person.yes.iter() {
+                *frequencies.entry(c).or_insert(0) += 1;
+            }
         }
-        yes.len()
+        frequencies
+    }
+
+    // How many questions at least k people answered yes to.
+    fn at_least_count(&self, k: usize) -> usize {
+        self.frequencies()
+            .values()
+            .filter(|&&count| count >= k)
+            .count()
+    }
+
+    fn unanswered(&self) -> Vec<char> {
+        let yes = self.any_yes();
+        QUESTIONS.chars().filter(|&c| !yes.contains(c)).collect()
+    }
+
+    fn print_stats(&self) {
+        let frequencies: Vec<String> = self
+            .frequencies()
+            .iter()
+            .map(|(c, count)| format!("{}:{}", c, count))
+            .collect();
+        println!("  frequencies: {}", frequencies.join(" "));
+
+        let at_least: Vec<String> = (1..=self.people.len())
+            .map(|k| format!("{}:{}", k, self.at_least_count(k)))
+            .collect();
+        println!(
+            "  at least k of {}: {}",
+            self.people.len(),
+            at_least.join(" ")
+        );
+
+        let unanswered: String = self.unanswered().into_iter().collect();
+        println!("  unanswered: {}", unanswered);
     }
 }
 
-pub(crate) fn day06() {
+pub(crate) fn day06(group_stats: bool) {
     let input = std::fs::read_to_string("data/day06.txt").unwrap();
 
     let mut groups: Vec<Group> = vec![];
@@ -65,4 +180,11 @@ pub(crate) fn day06() {
 
     let all_yes_count: usize = groups.iter().map(|group| group.all_yes_count()).sum();
     println!("Part two answer is {}", all_yes_count);
+
+    if group_stats {
+        for (index, group) in groups.iter().enumerate() {
+            println!("Group {}:", index);
+            group.print_stats();
+        }
+    }
 }
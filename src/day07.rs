@@ -1,83 +1,443 @@
+use nom::branch::alt;
+use nom::bytes::complete::tag;
+use nom::character::complete::{alpha1, char, digit1};
+use nom::combinator::{cut, eof, map_res, not, opt, recognize, value, verify};
+use nom::error::{context, VerboseError, VerboseErrorKind};
+use nom::multi::separated_list1;
+use nom::sequence::terminated;
+use nom::IResult;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fmt::Display;
+use std::path::Path;
 
-#[derive(Eq, PartialEq, Hash)]
-struct Bag {
-    adjective: String,
-    colour: String,
+struct Rule {
+    container: String,
+    contents: Vec<(usize, String)>,
 }
 
-impl Bag {
-    fn new(adjective: String, colour: String) -> Self {
-        Self { adjective, colour }
+#[derive(Debug)]
+struct ParseError {
+    line: usize,
+    column: usize,
+    expected: String,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: expected {}",
+            self.line, self.column, self.expected
+        )
     }
 }
 
-fn parse_rules(input: &str) -> HashMap<Bag, Vec<(usize, Bag)>> {
-    let mut rules = HashMap::new();
+type ParseResult<'a, T> = IResult<&'a str, T, VerboseError<&'a str>>;
+
+// One or more words, stopping before "bag" or "bags".
+fn colour(input: &str) -> ParseResult<'_, &str> {
+    let word = verify(alpha1, |word: &str| word != "bag" && word != "bags");
+    context("colour", recognize(separated_list1(char(' '), word)))(input)
+}
+
+// Expects input like "1 bright white bag" or "2 muted yellow bags".
+fn contained(input: &str) -> ParseResult<'_, (usize, String)> {
+    let (input, count) = context("count", map_res(digit1, str::parse::<usize>))(input)?;
+    let (input, _) = char(' ')(input)?;
+    let (input, colour) = colour(input)?;
+    let (input, _) = if count == 1 {
+        context("\" bag\"", terminated(tag(" bag"), not(alpha1)))(input)?
+    } else {
+        context("\" bags\"", tag(" bags"))(input)?
+    };
+    Ok((input, (count, colour.to_owned())))
+}
 
-    for line in input.lines() {
-        let mut words = line.split_whitespace();
+fn contents(input: &str) -> ParseResult<'_, Vec<(usize, String)>> {
+    alt((
+        value(vec![], tag("no other bags")),
+        separated_list1(tag(", "), cut(contained)),
+    ))(input)
+}
 
-        let adjective = words.next().unwrap();
-        let colour = words.next().unwrap();
-        let container = Bag::new(adjective.to_owned(), colour.to_owned());
+// Expects input like "light red bags contain 1 bright white bag, 2 muted yellow bags."
+fn rule(input: &str) -> ParseResult<'_, Rule> {
+    let (input, container) = colour(input)?;
+    let (input, _) = context("\" bags contain \"", tag(" bags contain "))(input)?;
+    let (input, contents) = cut(contents)(input)?;
+    let (input, _) = opt(char('.'))(input)?;
+    let (input, _) = context("end of rule", eof)(input)?;
+    let rule = Rule {
+        container: container.to_owned(),
+        contents,
+    };
+    Ok((input, rule))
+}
 
-        let _bags = words.next().unwrap();
-        let _contains = words.next().unwrap();
+fn parse_error(line: usize, text: &str, err: nom::Err<VerboseError<&str>>) -> ParseError {
+    let errors = match err {
+        nom::Err::Error(err) | nom::Err::Failure(err) => err.errors,
+        nom::Err::Incomplete(_) => vec![],
+    };
 
-        let mut contains = vec![];
-        while let Some(count) = words.next().and_then(|w| w.parse::<usize>().ok()) {
-            let adjective = words.next().unwrap();
-            let colour = words.next().unwrap();
-            let contained = Bag::new(adjective.to_owned(), colour.to_owned());
-            contains.push((count, contained));
+    // Report the innermost context, falling back on whatever nom has to say.
+    let context = errors.iter().find_map(|(rest, kind)| match kind {
+        VerboseErrorKind::Context(context) => Some((*rest, context.to_string())),
+        _ => None,
+    });
+    let (rest, expected) = context
+        .or_else(|| {
+            errors
+                .first()
+                .map(|(rest, kind)| (*rest, format!("{:?}", kind)))
+        })
+        .unwrap_or(("", "rule".to_owned()));
 
-            let _bags = words.next().unwrap();
-        }
-        rules.insert(container, contains);
+    ParseError {
+        line,
+        column: 1 + text.len() - rest.len(),
+        expected,
     }
-    rules
 }
 
-pub(crate) fn day07() {
-    let input = std::fs::read_to_string("data/day07.txt").unwrap();
-    let rules = parse_rules(&input);
+// Rules are listed in the order given, so that duplicate definitions can be reported.
+fn parse_rules(input: &str) -> Result<Vec<Rule>, ParseError> {
+    input
+        .lines()
+        .enumerate()
+        .map(|(index, line)| {
+            rule(line)
+                .map(|(_rest, rule)| rule)
+                .map_err(|err| parse_error(index + 1, line, err))
+        })
+        .collect()
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+struct BagId(usize);
+
+enum Problem {
+    Cycle(Vec<BagId>),
+    Undefined(BagId),
+    Duplicate(BagId),
+}
+
+enum CountError {
+    Cycle(Vec<BagId>),
+    Overflow,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum Visit {
+    New,
+    InProgress,
+    Done,
+}
+
+// Depth-first search state.
+struct Search {
+    visits: Vec<Visit>,
+    stack: Vec<BagId>,
+    postorder: Vec<BagId>,
+    cycles: Vec<Vec<BagId>>,
+}
+
+// The rules, with each bag interned as an index into the lists of names and edges.
+#[derive(Default)]
+struct BagGraph {
+    names: Vec<String>,
+    ids: HashMap<String, BagId>,
+
+    // What each bag contains, and what each bag is contained by.
+    contents: Vec<Vec<(usize, BagId)>>,
+    containers: Vec<Vec<BagId>>,
+
+    // How many rules define each bag.  Only the first definition is used.
+    definitions: Vec<usize>,
+}
+
+impl BagGraph {
+    fn new(rules: &[Rule]) -> Self {
+        let mut graph = Self::default();
+        for rule in rules {
+            let container = graph.intern(&rule.container);
+            graph.definitions[container.0] += 1;
+            if graph.definitions[container.0] > 1 {
+                continue;
+            }
+            for (count, contained) in &rule.contents {
+                let contained = graph.intern(contained);
+                graph.contents[container.0].push((*count, contained));
+                graph.containers[contained.0].push(container);
+            }
+        }
+        graph
+    }
+
+    fn intern(&mut self, name: &str) -> BagId {
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+        let id = BagId(self.names.len());
+        self.names.push(name.to_owned());
+        self.ids.insert(name.to_owned(), id);
+        self.contents.push(vec![]);
+        self.containers.push(vec![]);
+        self.definitions.push(0);
+        id
+    }
+
+    fn id(&self, name: &str) -> Option<BagId> {
+        self.ids.get(name).copied()
+    }
+
+    fn name(&self, id: BagId) -> &str {
+        &self.names[id.0]
+    }
+
+    fn ids(&self) -> impl Iterator<Item = BagId> {
+        (0..self.names.len()).map(BagId)
+    }
+
+    fn search(&self, roots: impl Iterator<Item = BagId>) -> Search {
+        let mut search = Search {
+            visits: vec![Visit::New; self.names.len()],
+            stack: vec![],
+            postorder: vec![],
+            cycles: vec![],
+        };
+        for root in roots {
+            if search.visits[root.0] == Visit::New {
+                self.visit(root, &mut search);
+            }
+        }
+        search
+    }
+
+    fn visit(&self, bag: BagId, search: &mut Search) {
+        search.visits[bag.0] = Visit::InProgress;
+        search.stack.push(bag);
+        for &(_count, inside) in &self.contents[bag.0] {
+            match search.visits[inside.0] {
+                Visit::New => self.visit(inside, search),
+                Visit::InProgress => {
+                    let start = search.stack.iter().position(|&b| b == inside).unwrap();
+                    search.cycles.push(search.stack[start..].to_vec());
+                }
+                Visit::Done => {}
+            }
+        }
+        search.stack.pop();
+        search.visits[bag.0] = Visit::Done;
+        search.postorder.push(bag);
+    }
+
+    // Bags reachable from this one, each appearing before anything it contains.
+    fn topological_order(&self, bag: BagId) -> Result<Vec<BagId>, CountError> {
+        let mut search = self.search(std::iter::once(bag));
+        if let Some(cycle) = search.cycles.pop() {
+            return Err(CountError::Cycle(cycle));
+        }
+        search.postorder.reverse();
+        Ok(search.postorder)
+    }
+
+    fn validate(&self) -> Vec<Problem> {
+        let mut problems: Vec<Problem> = self
+            .search(self.ids())
+            .cycles
+            .into_iter()
+            .map(Problem::Cycle)
+            .collect();
+        for bag in self.ids() {
+            match self.definitions[bag.0] {
+                0 => problems.push(Problem::Undefined(bag)),
+                1 => {}
+                _ => problems.push(Problem::Duplicate(bag)),
+            }
+        }
+        problems
+    }
+
+    fn describe_cycle(&self, cycle: &[BagId]) -> String {
+        let names: Vec<&str> = cycle
+            .iter()
+            .chain(cycle.first())
+            .map(|&bag| self.name(bag))
+            .collect();
+        names.join(" -> ")
+    }
+
+    fn describe(&self, problem: &Problem) -> String {
+        match problem {
+            Problem::Cycle(cycle) => {
+                format!("bags contain themselves: {}", self.describe_cycle(cycle))
+            }
+            Problem::Undefined(bag) => format!("no rule for {} bags", self.name(*bag)),
+            Problem::Duplicate(bag) => format!("more than one rule for {} bags", self.name(*bag)),
+        }
+    }
 
-    // Rules tell us what each bag contains, but we want to know what each bag is contained by.
-    let mut inversion = HashMap::<&Bag, Vec<&Bag>>::new();
-    for (container, rule) in &rules {
-        for (_count, contained) in rule {
-            let containers = inversion.entry(contained).or_insert(vec![]);
-            containers.push(&container);
+    fn describe_count_error(&self, err: &CountError) -> String {
+        match err {
+            CountError::Cycle(cycle) => {
+                format!("bags contain themselves: {}", self.describe_cycle(cycle))
+            }
+            CountError::Overflow => "too many bags to count".to_owned(),
         }
     }
 
-    // Search from the shiny gold bag.
-    let shiny_gold = Bag::new("shiny".to_owned(), "gold".to_owned());
-    let mut visited = HashSet::<&Bag>::new();
-    let mut queue = vec![&shiny_gold];
-    while let Some(bag) = queue.pop() {
-        if let Some(containers) = inversion.get(bag) {
-            for container in containers {
+    // Every bag that eventually contains this one.
+    fn containers(&self, bag: BagId) -> HashSet<BagId> {
+        let mut visited = HashSet::new();
+        let mut queue = vec![bag];
+        while let Some(bag) = queue.pop() {
+            for &container in &self.containers[bag.0] {
                 if visited.insert(container) {
                     queue.push(container);
                 }
             }
         }
+        visited
+    }
+
+    // How many of each bag this one eventually contains.
+    //
+    // Working through the bags in topological order means that we visit each bag once, with the
+    // full count of how many times it appears.
+    fn contents(&self, bag: BagId) -> Result<HashMap<BagId, usize>, CountError> {
+        let mut counts: HashMap<BagId, usize> = HashMap::new();
+        counts.insert(bag, 1);
+        for outer in self.topological_order(bag)? {
+            let outer_count = counts[&outer];
+            for &(count, inside) in &self.contents[outer.0] {
+                let extra = outer_count.checked_mul(count).ok_or(CountError::Overflow)?;
+                let total = counts.entry(inside).or_insert(0);
+                *total = total.checked_add(extra).ok_or(CountError::Overflow)?;
+            }
+        }
+        counts.remove(&bag);
+        Ok(counts)
+    }
+
+    fn count_inside(&self, bag: BagId) -> Result<usize, CountError> {
+        self.contents(bag)?
+            .values()
+            .try_fold(0usize, |total, &count| total.checked_add(count))
+            .ok_or(CountError::Overflow)
+    }
+
+    // How many levels of bags are nested inside this one.
+    fn depth(&self, bag: BagId) -> Result<usize, CountError> {
+        let mut depths = HashMap::new();
+        for outer in self.topological_order(bag)?.into_iter().rev() {
+            let depth = self.contents[outer.0]
+                .iter()
+                .map(|(_count, inside)| 1 + depths[inside])
+                .max()
+                .unwrap_or(0);
+            depths.insert(outer, depth);
+        }
+        Ok(depths[&bag])
+    }
+
+    // The shortest chain of bags leading from the outer bag down to the inner one.
+    fn path(&self, outer: BagId, inner: BagId) -> Option<Vec<BagId>> {
+        let mut previous = HashMap::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(outer);
+        while let Some(bag) = queue.pop_front() {
+            if bag == inner {
+                let mut path = vec![bag];
+                while let Some(&before) = previous.get(path.last().unwrap()) {
+                    path.push(before);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for &(_count, inside) in &self.contents[bag.0] {
+                if inside != outer && !previous.contains_key(&inside) {
+                    previous.insert(inside, bag);
+                    queue.push_back(inside);
+                }
+            }
+        }
+        None
+    }
+
+    // Graphviz, with edges labelled by how many of the inner bag the outer one holds.
+    fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph bags {\n");
+        for bag in self.ids() {
+            dot.push_str(&format!("    \"{}\";\n", self.name(bag)));
+        }
+        for outer in self.ids() {
+            for &(count, inner) in &self.contents[outer.0] {
+                dot.push_str(&format!(
+                    "    \"{}\" -> \"{}\" [label={}];\n",
+                    self.name(outer),
+                    self.name(inner),
+                    count
+                ));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+pub(crate) fn day07(bag: Option<&str>, path_to: Option<&str>, dot: Option<&Path>) {
+    let input = std::fs::read_to_string("data/day07.txt").unwrap();
+    let rules = parse_rules(&input).unwrap_or_else(|err| panic!("bad rules: {}", err));
+    let graph = BagGraph::new(&rules);
+    for problem in graph.validate() {
+        println!("Bad rules: {}", graph.describe(&problem));
+    }
+
+    if let Some(path) = dot {
+        std::fs::write(path, graph.to_dot()).unwrap();
+    }
+
+    let name = bag.unwrap_or("shiny gold");
+    let id = graph
+        .id(name)
+        .unwrap_or_else(|| panic!("no rule for {} bags", name));
+
+    let containers = graph.containers(id);
+    println!("Part one answer is {}", containers.len());
+
+    match graph.count_inside(id) {
+        Ok(total) => println!("Part two answer is {}", total),
+        Err(err) => println!(
+            "Part two has no answer: {}",
+            graph.describe_count_error(&err)
+        ),
+    }
+
+    if bag.is_some() {
+        match graph.depth(id) {
+            Ok(depth) => println!("{} bags nest {} deep", name, depth),
+            Err(err) => println!(
+                "{} bags have no depth: {}",
+                name,
+                graph.describe_count_error(&err)
+            ),
+        }
     }
-    println!("Part one answer is {}", visited.len());
 
-    // This time we search on the rules as given.
-    let mut total = 0;
-    let mut queue = vec![(1, &shiny_gold)];
-    while let Some((bag_count, bag)) = queue.pop() {
-        total += bag_count;
-        for (count, inside) in rules.get(bag).unwrap() {
-            queue.push((count * bag_count, inside));
+    if let Some(inner) = path_to {
+        let inner_id = graph
+            .id(inner)
+            .unwrap_or_else(|| panic!("no rule for {} bags", inner));
+        match graph.path(id, inner_id) {
+            Some(path) => {
+                let names: Vec<&str> = path.iter().map(|&bag| graph.name(bag)).collect();
+                println!("{}", names.join(" -> "));
+            }
+            None => println!("{} bags do not contain {} bags", name, inner),
         }
     }
-    // Don't count the shiny gold bag.
-    total -= 1;
-    println!("Part two answer is {}", total);
 }
@@ -1,48 +1,229 @@
+use std::collections::BTreeSet;
+use std::collections::HashMap;
 use std::collections::HashSet;
-use std::str::FromStr;
+use std::fmt;
+use std::fmt::Display;
+use std::io::BufRead;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+// What executing an instruction does to the instruction pointer.
+enum Effect {
+    Next,
+    Jump(isize),
+    Halt,
+}
+
+// What an opcode might do to the instruction pointer, without knowing the accumulator.
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum Flow {
+    Next,
+    Jump,
+    Branch,
+    Halt,
+}
+
+// Given the accumulator and the argument, update the accumulator and say where to go next.
+type Operation = fn(&mut isize, isize) -> Effect;
+
+#[derive(Clone)]
+struct Opcode {
+    name: String,
+    flow: Flow,
+    operation: Operation,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+struct OpcodeId(usize);
 
 #[derive(Copy, Clone, Eq, PartialEq)]
-enum Instruction {
-    Nop(isize),
-    Acc(isize),
-    Jmp(isize),
+struct Instruction {
+    opcode: OpcodeId,
+    arg: isize,
 }
 
 #[derive(Debug)]
 struct ParseError;
 
-impl FromStr for Instruction {
-    type Err = ParseError;
+#[derive(Clone, Default)]
+struct InstructionSet {
+    opcodes: Vec<Opcode>,
+    ids: HashMap<String, OpcodeId>,
+}
+
+impl InstructionSet {
+    // The instructions from the puzzle.
+    fn handheld() -> Self {
+        let mut set = Self::default();
+        set.register("nop", Flow::Next, |_acc, _arg| Effect::Next);
+        set.register("acc", Flow::Next, |acc, arg| {
+            *acc = acc.wrapping_add(arg);
+            Effect::Next
+        });
+        set.register("jmp", Flow::Jump, |_acc, arg| Effect::Jump(arg));
+        set
+    }
+
+    // Some more instructions that later puzzles might want.
+    fn extended() -> Self {
+        let mut set = Self::handheld();
+        set.register("mul", Flow::Next, |acc, arg| {
+            *acc = acc.wrapping_mul(arg);
+            Effect::Next
+        });
+        set.register("jz", Flow::Branch, |acc, arg| match acc {
+            0 => Effect::Jump(arg),
+            _ => Effect::Next,
+        });
+        set.register("jnz", Flow::Branch, |acc, arg| match acc {
+            0 => Effect::Next,
+            _ => Effect::Jump(arg),
+        });
+        set.register("hlt", Flow::Halt, |_acc, _arg| Effect::Halt);
+        set
+    }
+
+    // Registering an existing name replaces its behaviour.
+    fn register(&mut self, name: &str, flow: Flow, operation: Operation) -> OpcodeId {
+        let opcode = Opcode {
+            name: name.to_owned(),
+            flow,
+            operation,
+        };
+        if let Some(&id) = self.ids.get(name) {
+            self.opcodes[id.0] = opcode;
+            return id;
+        }
+        let id = OpcodeId(self.opcodes.len());
+        self.opcodes.push(opcode);
+        self.ids.insert(name.to_owned(), id);
+        id
+    }
+
+    fn id(&self, name: &str) -> Option<OpcodeId> {
+        self.ids.get(name).copied()
+    }
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
+    fn opcode(&self, id: OpcodeId) -> &Opcode {
+        &self.opcodes[id.0]
+    }
+
+    // Expects input like "jmp -4".
+    fn parse(&self, s: &str) -> Result<Instruction, ParseError> {
         let mut words = s.split_whitespace();
-        let op = words.next().ok_or(ParseError)?;
+        let name = words.next().ok_or(ParseError)?;
+        let opcode = self.id(name).ok_or(ParseError)?;
         let arg: isize = words
             .next()
             .and_then(|w| w.parse().ok())
             .ok_or(ParseError)?;
-        let instruction = match op {
-            "nop" => Self::Nop(arg),
-            "acc" => Self::Acc(arg),
-            "jmp" => Self::Jmp(arg),
-            _ => return Err(ParseError),
+        Ok(Instruction { opcode, arg })
+    }
+
+    // The puzzle's corruption swaps nop and jmp.
+    fn flipped(&self, instruction: Instruction) -> Option<Instruction> {
+        let nop = self.id("nop")?;
+        let jmp = self.id("jmp")?;
+        let opcode = if instruction.opcode == nop {
+            jmp
+        } else if instruction.opcode == jmp {
+            nop
+        } else {
+            return None;
         };
-        Ok(instruction)
+        Some(Instruction {
+            opcode,
+            ..instruction
+        })
+    }
+
+    fn format(&self, instruction: Instruction) -> String {
+        let name = &self.opcode(instruction.opcode).name;
+        format!("{} {:+}", name, instruction.arg)
+    }
+}
+
+// How many distinct states a program may pass through before we stop waiting for it.
+const STEP_LIMIT: usize = 1_000_000;
+
+#[derive(Copy, Clone)]
+enum ExecutionResult {
+    // Ran off the end of the program.
+    Terminated(isize),
+
+    // Executed a halt instruction.
+    Halted(isize),
+
+    // Tried to go to an instruction outside the program.
+    OutOfRange { target: isize, accumulator: isize },
+
+    // Reached a state that we've been in before.
+    InfiniteLoop { ip: usize, accumulator: isize },
+
+    // Ran for STEP_LIMIT instructions without repeating a state or stopping.
+    StepLimit { ip: usize, accumulator: isize },
+}
+
+impl ExecutionResult {
+    fn accumulator(&self) -> isize {
+        match *self {
+            Self::Terminated(accumulator) | Self::Halted(accumulator) => accumulator,
+            Self::OutOfRange { accumulator, .. }
+            | Self::InfiniteLoop { accumulator, .. }
+            | Self::StepLimit { accumulator, .. } => accumulator,
+        }
+    }
+}
+
+impl Display for ExecutionResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Terminated(accumulator) => {
+                write!(f, "terminated with accumulator {}", accumulator)
+            }
+            Self::Halted(accumulator) => write!(f, "halted with accumulator {}", accumulator),
+            Self::OutOfRange {
+                target,
+                accumulator,
+            } => write!(
+                f,
+                "jumped out of range to {} with accumulator {}",
+                target, accumulator
+            ),
+            Self::InfiniteLoop { ip, accumulator } => write!(
+                f,
+                "looping at instruction {} with accumulator {}",
+                ip, accumulator
+            ),
+            Self::StepLimit { ip, accumulator } => write!(
+                f,
+                "still running after {} steps, at instruction {} with accumulator {}",
+                STEP_LIMIT, ip, accumulator
+            ),
+        }
     }
 }
 
 struct Computer {
+    set: InstructionSet,
     instructions: Vec<Instruction>,
     instruction_pointer: usize,
     accumulator: isize,
+
+    // Log every instruction executed.
+    trace: bool,
 }
 
 impl Computer {
-    fn new(instructions: Vec<Instruction>) -> Self {
+    fn new(set: InstructionSet, instructions: Vec<Instruction>) -> Self {
         Self {
+            set,
             instructions,
             instruction_pointer: 0,
             accumulator: 0,
+            trace: false,
         }
     }
 
@@ -51,79 +232,633 @@ impl Computer {
         self.instruction_pointer = 0;
     }
 
-    fn toggle_at_ip(&mut self) -> bool {
-        let position = self.instruction_pointer;
-        match self.instructions[position] {
-            Instruction::Nop(arg) => {
-                self.instructions[position] = Instruction::Jmp(arg);
-                true
+    fn current(&self) -> Option<Instruction> {
+        self.instructions.get(self.instruction_pointer).copied()
+    }
+
+    // Execute one instruction, returning a result if the program can't continue.  The instruction
+    // pointer only ever moves to a position inside the program, or just past the end.
+    fn step(&mut self) -> Option<ExecutionResult> {
+        let instruction = match self.current() {
+            Some(instruction) => instruction,
+            None => return Some(ExecutionResult::Terminated(self.accumulator)),
+        };
+        let operation = self.set.opcode(instruction.opcode).operation;
+        let effect = operation(&mut self.accumulator, instruction.arg);
+        if self.trace {
+            println!(
+                "ip {:<5} {:<10} acc {}",
+                self.instruction_pointer,
+                self.set.format(instruction),
+                self.accumulator
+            );
+        }
+        let offset = match effect {
+            Effect::Next => 1,
+            Effect::Jump(offset) => offset,
+            Effect::Halt => return Some(ExecutionResult::Halted(self.accumulator)),
+        };
+
+        let length = self.instructions.len();
+        let target = (self.instruction_pointer as isize).checked_add(offset);
+        match target.filter(|&t| t >= 0 && t as usize <= length) {
+            Some(target) => self.instruction_pointer = target as usize,
+            None => {
+                return Some(ExecutionResult::OutOfRange {
+                    target: target.unwrap_or(isize::MAX),
+                    accumulator: self.accumulator,
+                })
+            }
+        }
+        if self.instruction_pointer == length {
+            return Some(ExecutionResult::Terminated(self.accumulator));
+        }
+        None
+    }
+
+    // If the accumulator can't affect control flow then revisiting an instruction means that we're
+    // stuck; otherwise we have to see the same accumulator there too.  A branching program can
+    // keep changing the accumulator forever without repeating, so give up after STEP_LIMIT steps.
+    fn run(&mut self) -> ExecutionResult {
+        let static_flow = self
+            .instructions
+            .iter()
+            .all(|i| self.set.opcode(i.opcode).flow != Flow::Branch);
+        let mut visited: HashSet<(usize, isize)> = HashSet::new();
+        loop {
+            let ip = self.instruction_pointer;
+            let state = if static_flow {
+                (ip, 0)
+            } else {
+                (ip, self.accumulator)
+            };
+            if !visited.insert(state) {
+                return ExecutionResult::InfiniteLoop {
+                    ip,
+                    accumulator: self.accumulator,
+                };
+            }
+            if visited.len() > STEP_LIMIT {
+                return ExecutionResult::StepLimit {
+                    ip,
+                    accumulator: self.accumulator,
+                };
             }
-            Instruction::Jmp(arg) => {
-                self.instructions[position] = Instruction::Nop(arg);
-                true
+            if let Some(result) = self.step() {
+                return result;
             }
-            _ => false,
         }
     }
+}
+
+// A single nop/jmp flip that lets the program finish.
+struct Fix {
+    ip: usize,
+    result: ExecutionResult,
+}
+
+struct Analysis {
+    fixes: Vec<Fix>,
+
+    // Instructions that no path from the start reaches.
+    unreachable: Vec<usize>,
+}
+
+impl Analysis {
+    fn is_ambiguous(&self) -> bool {
+        self.fixes.len() > 1
+    }
+}
+
+// Where control might go after the instruction at ip.  The end of the program is position
+// `program.len()`, and jumps out of range go nowhere.
+fn successors(set: &InstructionSet, program: &[Instruction], ip: usize) -> Vec<usize> {
+    let instruction = program[ip];
+    let next = ip + 1;
+    let target = (ip as isize)
+        .checked_add(instruction.arg)
+        .filter(|&t| t >= 0 && t as usize <= program.len())
+        .map(|t| t as usize);
+    match set.opcode(instruction.opcode).flow {
+        Flow::Next => vec![next],
+        Flow::Jump => target.into_iter().collect(),
+        Flow::Branch => std::iter::once(next).chain(target).collect(),
+        Flow::Halt => vec![],
+    }
+}
+
+// Which instructions can be reached from the start.
+fn reachable(set: &InstructionSet, program: &[Instruction]) -> Vec<bool> {
+    let mut reached = vec![false; program.len() + 1];
+    let mut queue = vec![0];
+    reached[0] = true;
+    while let Some(ip) = queue.pop() {
+        if ip == program.len() {
+            continue;
+        }
+        for next in successors(set, program, ip) {
+            if !reached[next] {
+                reached[next] = true;
+                queue.push(next);
+            }
+        }
+    }
+    reached
+}
+
+// Which instructions have a path to the end of the program, or to a halt.  This works backwards
+// from the finishing points over the reversed control flow graph.
+fn can_finish(set: &InstructionSet, program: &[Instruction]) -> Vec<bool> {
+    let mut predecessors = vec![vec![]; program.len() + 1];
+    for ip in 0..program.len() {
+        for next in successors(set, program, ip) {
+            predecessors[next].push(ip);
+        }
+    }
+
+    let mut finishes = vec![false; program.len() + 1];
+    let mut queue = vec![program.len()];
+    for (ip, instruction) in program.iter().enumerate() {
+        if set.opcode(instruction.opcode).flow == Flow::Halt {
+            queue.push(ip);
+        }
+    }
+    for &ip in &queue {
+        finishes[ip] = true;
+    }
+    while let Some(ip) = queue.pop() {
+        for &before in &predecessors[ip] {
+            if !finishes[before] {
+                finishes[before] = true;
+                queue.push(before);
+            }
+        }
+    }
+    finishes
+}
+
+// Only a reachable instruction can make a difference when flipped, and only if the flipped version
+// leads somewhere that can finish.  Candidates are then run to confirm and to find the accumulator.
+fn analyse(set: &InstructionSet, program: &[Instruction]) -> Analysis {
+    let reached = reachable(set, program);
+    let finishes = can_finish(set, program);
+
+    let mut fixes = vec![];
+    for ip in (0..program.len()).filter(|&ip| reached[ip]) {
+        let flipped = match set.flipped(program[ip]) {
+            Some(flipped) => flipped,
+            None => continue,
+        };
+        let mut fixed = program.to_vec();
+        fixed[ip] = flipped;
+        if !successors(set, &fixed, ip)
+            .iter()
+            .any(|&next| finishes[next])
+        {
+            continue;
+        }
+
+        let mut computer = Computer::new(set.clone(), fixed);
+        match computer.run() {
+            result @ ExecutionResult::Terminated(_) | result @ ExecutionResult::Halted(_) => {
+                fixes.push(Fix { ip, result })
+            }
+            _ => {}
+        }
+    }
+
+    let unreachable = (0..program.len()).filter(|&ip| !reached[ip]).collect();
+    Analysis { fixes, unreachable }
+}
+
+// Collapse runs of consecutive instructions, like "3-7, 12".
+fn describe_ranges(ips: &[usize]) -> String {
+    let mut ranges: Vec<(usize, usize)> = vec![];
+    for &ip in ips {
+        match ranges.last_mut() {
+            Some((_start, end)) if *end + 1 == ip => *end = ip,
+            _ => ranges.push((ip, ip)),
+        }
+    }
+    let ranges: Vec<String> = ranges
+        .iter()
+        .map(|&(start, end)| match start == end {
+            true => start.to_string(),
+            false => format!("{}-{}", start, end),
+        })
+        .collect();
+    ranges.join(", ")
+}
+
+fn print_analysis(set: &InstructionSet, program: &[Instruction], analysis: &Analysis) {
+    for fix in &analysis.fixes {
+        println!(
+            "Flipping instruction {} ({}) {}",
+            fix.ip,
+            set.format(program[fix.ip]),
+            fix.result
+        );
+    }
+    if analysis.fixes.is_empty() {
+        println!("No single flip lets the program finish");
+    }
+    if analysis.is_ambiguous() {
+        println!("Ambiguous: {} different flips work", analysis.fixes.len());
+    }
+    if !analysis.unreachable.is_empty() {
+        println!(
+            "Unreachable instructions: {}",
+            describe_ranges(&analysis.unreachable)
+        );
+    }
+}
+
+#[derive(Debug)]
+struct AssembleError {
+    line: usize,
+    message: String,
+}
+
+impl Display for AssembleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+fn is_label(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
 
-    fn step(&mut self) {
-        let instruction = &self.instructions[self.instruction_pointer];
-        match instruction {
-            Instruction::Nop(_) => self.instruction_pointer += 1,
-            Instruction::Acc(arg) => {
-                self.accumulator += arg;
-                self.instruction_pointer += 1;
+// Source like "loop: jmp loop  # comment".  A label on a line of its own belongs to the next
+// instruction, or to the end of the program.  Label arguments are replaced by the offset from the
+// instruction to the label.
+fn assemble(set: &InstructionSet, source: &str) -> Result<Vec<Instruction>, AssembleError> {
+    let mut labels: HashMap<&str, usize> = HashMap::new();
+    let mut statements: Vec<(usize, &str, &str)> = vec![];
+    for (index, line) in source.lines().enumerate() {
+        let error = |message: String| AssembleError {
+            line: index + 1,
+            message,
+        };
+        let mut text = line.split('#').next().unwrap().trim();
+        if let Some((label, rest)) = text.split_once(':') {
+            let label = label.trim();
+            if !is_label(label) {
+                return Err(error(format!("bad label {:?}", label)));
             }
-            Instruction::Jmp(arg) => {
-                // yuk
-                self.instruction_pointer = (self.instruction_pointer as isize + arg) as usize
+            if labels.insert(label, statements.len()).is_some() {
+                return Err(error(format!("duplicate label {}", label)));
             }
+            text = rest.trim();
+        }
+        if text.is_empty() {
+            continue;
+        }
+        let mut words = text.split_whitespace();
+        let name = words.next().unwrap();
+        let arg = words
+            .next()
+            .ok_or_else(|| error(format!("{} needs an argument", name)))?;
+        if words.next().is_some() {
+            return Err(error(format!("too many arguments to {}", name)));
+        }
+        statements.push((index + 1, name, arg));
+    }
+
+    statements
+        .iter()
+        .enumerate()
+        .map(|(ip, &(line, name, arg))| {
+            let error = |message: String| AssembleError { line, message };
+            let opcode = set
+                .id(name)
+                .ok_or_else(|| error(format!("unknown instruction {}", name)))?;
+            let arg = match arg.parse::<isize>() {
+                Ok(arg) => arg,
+                Err(_) => match labels.get(arg) {
+                    Some(&target) => target as isize - ip as isize,
+                    None => return Err(error(format!("unknown label {}", arg))),
+                },
+            };
+            Ok(Instruction { opcode, arg })
+        })
+        .collect()
+}
+
+// The inverse of assembly: jumps and branches that land inside the program refer to a label, and
+// every labelled instruction is annotated with its position.
+fn disassemble(set: &InstructionSet, program: &[Instruction]) -> String {
+    let target = |ip: usize, instruction: &Instruction| {
+        let flow = set.opcode(instruction.opcode).flow;
+        if flow != Flow::Jump && flow != Flow::Branch {
+            return None;
+        }
+        (ip as isize)
+            .checked_add(instruction.arg)
+            .filter(|&t| t >= 0 && t as usize <= program.len())
+            .map(|t| t as usize)
+    };
+    let targets: BTreeSet<usize> = program
+        .iter()
+        .enumerate()
+        .filter_map(|(ip, instruction)| target(ip, instruction))
+        .collect();
+
+    let mut text = String::new();
+    for (ip, instruction) in program.iter().enumerate() {
+        let label = match targets.contains(&ip) {
+            true => format!("l{}:", ip),
+            false => String::new(),
+        };
+        let name = &set.opcode(instruction.opcode).name;
+        let line = match target(ip, instruction) {
+            Some(to) => format!("{:<7} {} l{}", label, name, to),
+            None => format!("{:<7} {}", label, set.format(*instruction)),
+        };
+        text.push_str(&format!("{:<24} # {}\n", line, ip));
+    }
+    if targets.contains(&program.len()) {
+        text.push_str(&format!("l{}:\n", program.len()));
+    }
+    text
+}
+
+#[derive(Copy, Clone)]
+enum Watchpoint {
+    Change,
+    Equals(isize),
+}
+
+impl Display for Watchpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Change => write!(f, "accumulator changes"),
+            Self::Equals(value) => write!(f, "accumulator becomes {}", value),
         }
     }
+}
+
+enum Stop {
+    Breakpoint(usize),
+    Watchpoint(Watchpoint, isize, isize),
+    Repeat(usize),
+    Finished(ExecutionResult),
+    Stepped,
+}
 
-    fn run_to_repeat_point(&mut self) -> isize {
-        let mut visited: HashSet<usize> = HashSet::new();
-        while visited.insert(self.instruction_pointer) {
-            self.step();
+impl Display for Stop {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Breakpoint(ip) => write!(f, "breakpoint at {}", ip),
+            Self::Watchpoint(watch, old, new) => write!(f, "{}: {} -> {}", watch, old, new),
+            Self::Repeat(ip) => write!(f, "about to repeat instruction {}", ip),
+            Self::Finished(result) => write!(f, "{}", result),
+            Self::Stepped => Ok(()),
         }
-        self.accumulator
     }
+}
+
+const DEBUGGER_HELP: &str = "\
+break <ip>      stop before executing the instruction at ip
+delete <ip>     remove a breakpoint
+watch [value]   stop when the accumulator changes, or becomes value
+unwatch         remove all watchpoints
+step [n]        execute n instructions (default 1)
+continue        run until something stops us
+history [n]     show the last n instruction pointers (default 10)
+print           show the current state
+reset           start the program again
+quit            leave the debugger";
 
-    fn fix_and_terminate(&mut self) -> isize {
-        let mut corrupt_ip: Option<usize> = None;
-        let mut reset_acc = 0;
+struct Debugger {
+    computer: Computer,
+    breakpoints: BTreeSet<usize>,
+    watchpoints: Vec<Watchpoint>,
 
-        let program_length = self.instructions.len();
-        let mut visited: HashSet<usize> = HashSet::new();
+    // Every instruction pointer that we've executed, in order.
+    history: Vec<usize>,
+}
+
+impl Debugger {
+    fn new(computer: Computer) -> Self {
+        Self {
+            computer,
+            breakpoints: BTreeSet::new(),
+            watchpoints: vec![],
+            history: vec![],
+        }
+    }
 
-        // We'll execute each instruction at most twice: once on the original program and once on a
-        // version where we've fixed a single operation.
-        while self.instruction_pointer != program_length {
-            if !visited.insert(self.instruction_pointer) && corrupt_ip.is_some() {
-                // Found a loop, we've taken a wrong path, back up.
-                self.accumulator = reset_acc;
-                self.instruction_pointer = corrupt_ip.take().unwrap();
-                self.toggle_at_ip();
-            } else if corrupt_ip.is_none() && self.toggle_at_ip() {
-                // Found a new path to try.
-                corrupt_ip = Some(self.instruction_pointer);
-                reset_acc = self.accumulator;
+    fn reset(&mut self) {
+        self.computer.reset();
+        self.history.clear();
+    }
+
+    fn step(&mut self) -> Option<Stop> {
+        let ip = self.computer.instruction_pointer;
+        if ip < self.computer.instructions.len() {
+            self.history.push(ip);
+        }
+
+        let before = self.computer.accumulator;
+        if let Some(result) = self.computer.step() {
+            return Some(Stop::Finished(result));
+        }
+        let after = self.computer.accumulator;
+
+        self.watchpoints
+            .iter()
+            .find(|watch| match watch {
+                Watchpoint::Change => before != after,
+                Watchpoint::Equals(value) => before != after && after == *value,
+            })
+            .map(|&watch| Stop::Watchpoint(watch, before, after))
+    }
+
+    fn step_n(&mut self, count: usize) -> Stop {
+        for _ in 0..count {
+            if let Some(stop) = self.step() {
+                return stop;
+            }
+        }
+        Stop::Stepped
+    }
+
+    fn run(&mut self) -> Stop {
+        let mut visited: HashSet<usize> = self.history.iter().cloned().collect();
+        loop {
+            if let Some(stop) = self.step() {
+                return stop;
+            }
+            let ip = self.computer.instruction_pointer;
+            if self.breakpoints.contains(&ip) {
+                return Stop::Breakpoint(ip);
+            }
+            if !visited.insert(ip) {
+                return Stop::Repeat(ip);
+            }
+        }
+    }
+
+    fn print_state(&self) {
+        let ip = self.computer.instruction_pointer;
+        let instruction = self
+            .computer
+            .current()
+            .map_or("<none>".to_owned(), |i| self.computer.set.format(i));
+        println!(
+            "ip {}: {}, accumulator {}",
+            ip, instruction, self.computer.accumulator
+        );
+    }
+
+    // Returns false when it's time to quit.
+    fn execute(&mut self, command: &str) -> bool {
+        let mut words = command.split_whitespace();
+        let verb = match words.next() {
+            Some(verb) => verb,
+            None => return true,
+        };
+        let arg = words.next();
+        let number = arg.and_then(|arg| arg.parse::<usize>().ok());
+        match (verb, arg) {
+            ("break", Some(_)) | ("b", Some(_)) => match number {
+                Some(ip) => {
+                    self.breakpoints.insert(ip);
+                }
+                None => println!("Bad instruction pointer"),
+            },
+            ("delete", Some(_)) | ("d", Some(_)) => match number {
+                Some(ip) if self.breakpoints.remove(&ip) => {}
+                _ => println!("No such breakpoint"),
+            },
+            ("watch", None) | ("w", None) => self.watchpoints.push(Watchpoint::Change),
+            ("watch", Some(value)) | ("w", Some(value)) => match value.parse() {
+                Ok(value) => self.watchpoints.push(Watchpoint::Equals(value)),
+                Err(_) => println!("Bad accumulator value"),
+            },
+            ("unwatch", None) => self.watchpoints.clear(),
+            ("step", _) | ("s", _) => {
+                let stop = self.step_n(number.unwrap_or(1));
+                if !matches!(stop, Stop::Stepped) {
+                    println!("Stopped: {}", stop);
+                }
+                self.print_state();
+            }
+            ("continue", None) | ("c", None) => {
+                let stop = self.run();
+                println!("Stopped: {}", stop);
+                self.print_state();
+            }
+            ("history", _) | ("h", _) => {
+                let count = number.unwrap_or(10);
+                let start = self.history.len().saturating_sub(count);
+                let recent: Vec<String> = self.history[start..]
+                    .iter()
+                    .map(|ip| ip.to_string())
+                    .collect();
+                println!("{}", recent.join(" "));
+            }
+            ("print", None) | ("p", None) => self.print_state(),
+            ("reset", None) => {
+                self.reset();
+                self.print_state();
             }
-            self.step();
+            ("quit", None) | ("q", None) => return false,
+            _ => println!("{}", DEBUGGER_HELP),
         }
-        self.accumulator
+        true
     }
+
+    fn repl(&mut self) {
+        self.print_state();
+        let stdin = std::io::stdin();
+        loop {
+            print!("(debug) ");
+            std::io::stdout().flush().unwrap();
+            let mut line = String::new();
+            if stdin.lock().read_line(&mut line).unwrap() == 0 || !self.execute(&line) {
+                break;
+            }
+        }
+    }
+}
+
+fn parse_program(set: &InstructionSet, path: &Path) -> Vec<Instruction> {
+    let input = std::fs::read_to_string(path).unwrap();
+    input.lines().map(|line| set.parse(line).unwrap()).collect()
 }
 
-pub(crate) fn day08() {
-    let input = std::fs::read_to_string("data/day08.txt").unwrap();
-    let instructions: Vec<Instruction> = input.lines().map(|line| line.parse().unwrap()).collect();
+// Other things to do with the boot code, besides solving the puzzle.
+#[derive(StructOpt)]
+pub(crate) enum Command {
+    /// Debug the day 8 boot code interactively
+    Repl,
 
-    let mut computer = Computer::new(instructions);
-    let accumulator = computer.run_to_repeat_point();
-    println!("Part one answer is {}", accumulator);
+    /// Assemble day 8 source, with labels and comments, into boot code
+    Assemble {
+        #[structopt(parse(from_os_str))]
+        file: PathBuf,
+    },
 
-    computer.reset();
-    let accumulator = computer.fix_and_terminate();
-    println!("Part two answer is {}", accumulator);
+    /// Print day 8 boot code with jump targets as labels
+    Disassemble {
+        #[structopt(parse(from_os_str))]
+        file: Option<PathBuf>,
+    },
+}
+
+pub(crate) fn day08(command: Option<&Command>, analyse_program: bool, trace: bool) {
+    let set = InstructionSet::extended();
+    let puzzle = Path::new("data/day08.txt");
+
+    match command {
+        Some(Command::Repl) => {
+            let instructions = parse_program(&set, puzzle);
+            let mut debugger = Debugger::new(Computer::new(set, instructions));
+            debugger.repl();
+            return;
+        }
+        Some(Command::Assemble { file }) => {
+            let source = std::fs::read_to_string(file).unwrap();
+            let program =
+                assemble(&set, &source).unwrap_or_else(|err| panic!("bad assembly: {}", err));
+            for instruction in program {
+                println!("{}", set.format(instruction));
+            }
+            return;
+        }
+        Some(Command::Disassemble { file }) => {
+            let program = parse_program(&set, file.as_deref().unwrap_or(puzzle));
+            print!("{}", disassemble(&set, &program));
+            return;
+        }
+        None => {}
+    }
+
+    let instructions = parse_program(&set, puzzle);
+
+    let analysis = analyse(&set, &instructions);
+
+    let mut computer = Computer::new(set, instructions);
+    computer.trace = trace;
+    match computer.run() {
+        ExecutionResult::InfiniteLoop { accumulator, .. } => {
+            println!("Part one answer is {}", accumulator)
+        }
+        result => println!("Part one has no answer: program {}", result),
+    }
+
+    match analysis.fixes.first() {
+        Some(fix) => println!("Part two answer is {}", fix.result.accumulator()),
+        None => println!("Part two has no answer: no single flip terminates"),
+    }
+
+    if analyse_program {
+        print_analysis(&computer.set, &computer.instructions, &analysis);
+    }
 }
@@ -1,46 +1,176 @@
-use crate::utils::find_pair_summing_to;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fmt::Display;
+use std::io::BufRead;
+use std::num::ParseIntError;
+use std::ops::Range;
 
-const WINDOW_SIZE: usize = 25;
+const PREAMBLE: usize = 25;
 
-pub(crate) fn day09() {
-    let input = std::fs::read_to_string("data/day09.txt").unwrap();
-    let numbers: Vec<u64> = input.lines().map(|line| line.parse().unwrap()).collect();
+// The encryption weakness is in a run of at least two numbers.
+const MIN_RUN: usize = 2;
+
+// Keeps the most recent numbers as a multiset, so that checking for a pair is linear in the size
+// of the preamble.
+struct XmasValidator {
+    preamble: usize,
+    window: VecDeque<i64>,
+    counts: HashMap<i64, usize>,
+}
 
-    let mut target = 0;
-    for (window_start, window) in numbers.windows(WINDOW_SIZE).enumerate() {
-        let mut sorted = window.to_vec();
-        sorted.sort_unstable();
-        target = numbers[window_start + WINDOW_SIZE];
-        if find_pair_summing_to(target, &sorted).is_none() {
-            println!("Part one answer is {}", target);
-            break;
+impl XmasValidator {
+    fn new(preamble: usize) -> Self {
+        Self {
+            preamble,
+            window: VecDeque::with_capacity(preamble + 1),
+            counts: HashMap::new(),
         }
     }
 
-    let run = find_run_summing_to(target, &numbers).unwrap();
-    let min = run.iter().min().unwrap();
-    let max = run.iter().max().unwrap();
-    println!("Part two answer is {}", min + max);
+    // If working out the other half overflows then it can't be in the window.
+    fn has_pair_summing_to(&self, target: i64) -> bool {
+        self.window
+            .iter()
+            .any(|&number| match target.checked_sub(number) {
+                Some(other) => {
+                    let needed = if other == number { 2 } else { 1 };
+                    self.counts.get(&other).copied().unwrap_or(0) >= needed
+                }
+                None => false,
+            })
+    }
+
+    // Numbers in the preamble are always valid.
+    fn push(&mut self, number: i64) -> bool {
+        let valid = self.window.len() < self.preamble || self.has_pair_summing_to(number);
+
+        self.window.push_back(number);
+        *self.counts.entry(number).or_insert(0) += 1;
+        if self.window.len() > self.preamble {
+            let oldest = self.window.pop_front().unwrap();
+            let count = self.counts.get_mut(&oldest).unwrap();
+            *count -= 1;
+            if *count == 0 {
+                self.counts.remove(&oldest);
+            }
+        }
+        valid
+    }
+}
+
+// Every number that isn't the sum of two of the preceding ones, with its position.
+fn invalid_numbers(
+    preamble: usize,
+    numbers: impl Iterator<Item = i64>,
+) -> impl Iterator<Item = (usize, i64)> {
+    let mut validator = XmasValidator::new(preamble);
+    numbers
+        .enumerate()
+        .filter(move |&(_position, number)| !validator.push(number))
+}
+
+// A line of input that isn't a number.
+struct ParseError {
+    line: usize,
+    problem: ParseIntError,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.problem)
+    }
 }
 
-fn find_run_summing_to(target: u64, numbers: &[u64]) -> Option<&[u64]> {
-    let max_idx = numbers.len();
-    let mut lo = 0;
-    let mut hi = 0;
-    let mut total = 0;
-    loop {
-        if total == target {
-            return Some(&numbers[lo..hi]);
+pub(crate) fn day09(preamble: Option<usize>, stdin: bool, min_run: Option<usize>) {
+    let preamble = preamble.unwrap_or(PREAMBLE);
+
+    if stdin {
+        // Numbers are checked as they arrive, so stop at the first bad line rather than reading
+        // everything up front.
+        let mut error = None;
+        let numbers = std::io::stdin()
+            .lock()
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.unwrap()))
+            .filter(|(_line_number, line)| !line.trim().is_empty())
+            .map_while(|(line_number, line)| match line.trim().parse() {
+                Ok(number) => Some(number),
+                Err(problem) => {
+                    error = Some(ParseError {
+                        line: line_number,
+                        problem,
+                    });
+                    None
+                }
+            });
+        for (position, number) in invalid_numbers(preamble, numbers) {
+            println!("Invalid number {} at position {}", number, position);
+        }
+        if let Some(err) = error {
+            println!("Bad input: {}", err);
+        }
+        return;
+    }
+
+    let input = std::fs::read_to_string("data/day09.txt").unwrap();
+    let numbers: Vec<i64> = input.lines().map(|line| line.parse().unwrap()).collect();
+
+    let invalid: Vec<(usize, i64)> = invalid_numbers(preamble, numbers.iter().cloned()).collect();
+    for (position, number) in &invalid {
+        println!("Invalid number {} at position {}", number, position);
+    }
+    let target = match invalid.first() {
+        Some(&(_position, target)) => target,
+        None => {
+            println!("Part one has no answer: every number is valid");
+            return;
+        }
+    };
+    println!("Part one answer is {}", target);
+
+    let runs = match runs_summing_to(target, &numbers, min_run.unwrap_or(MIN_RUN)) {
+        Some(runs) => runs,
+        None => {
+            println!("Part two has no answer: the running total overflows");
+            return;
+        }
+    };
+    match runs.first() {
+        Some(run) => {
+            let run = &numbers[run.clone()];
+            let min = run.iter().min().unwrap();
+            let max = run.iter().max().unwrap();
+            println!("Part two answer is {}", min + max);
         }
+        None => println!("Part two has no answer: no run sums to {}", target),
+    }
+}
+
+// Every range of at least min_length consecutive numbers that sums to the target, ordered by where
+// the range starts.  Looking up earlier prefix sums, rather than sliding a window, means that
+// negative numbers are fine too.  Gives up if the running total overflows.
+fn runs_summing_to(target: i64, numbers: &[i64], min_length: usize) -> Option<Vec<Range<usize>>> {
+    let mut prefix_ends: HashMap<i64, Vec<usize>> = HashMap::new();
+    prefix_ends.insert(0, vec![0]);
 
-        if total > target {
-            total -= numbers[lo];
-            lo += 1;
-        } else if hi < max_idx {
-            total += numbers[hi];
-            hi += 1;
-        } else {
-            return None;
+    let mut runs = vec![];
+    let mut total: i64 = 0;
+    for (index, number) in numbers.iter().enumerate() {
+        total = total.checked_add(*number)?;
+        let end = index + 1;
+        let start_total = total.checked_sub(target);
+        if let Some(starts) = start_total.and_then(|start_total| prefix_ends.get(&start_total)) {
+            runs.extend(
+                starts
+                    .iter()
+                    .filter(|&&start| end - start >= min_length)
+                    .map(|&start| start..end),
+            );
         }
+        prefix_ends.entry(total).or_default().push(end);
     }
+    runs.sort_by_key(|run| (run.start, run.end));
+    Some(runs)
 }
@@ -1,25 +1,224 @@
-pub(crate) fn day10() {
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fmt::Display;
+use structopt::StructOpt;
+
+struct JoltageRules {
+    // Allowed differences between one adapter and the next.
+    gaps: Vec<usize>,
+
+    // How far the device's built-in adapter is above the highest adapter.
+    device_offset: usize,
+}
+
+impl Default for JoltageRules {
+    fn default() -> Self {
+        Self {
+            gaps: vec![1, 2, 3],
+            device_offset: 3,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum ChainError {
+    Gap { from: usize, to: usize },
+    NoArrangement,
+    Overflow,
+}
+
+impl Display for ChainError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Gap { from, to } => write!(f, "can't get from {} jolts to {} jolts", from, to),
+            Self::NoArrangement => write!(f, "no arrangement reaches the device"),
+            Self::Overflow => write!(f, "too many arrangements to count"),
+        }
+    }
+}
+
+// Which day 10 arrangements to print, besides the answers.
+#[derive(StructOpt)]
+pub(crate) struct ShowArrangements {
+    /// List the first n day 10 arrangements
+    #[structopt(long = "list-arrangements")]
+    list: Option<usize>,
+
+    /// Show the day 10 arrangement at this position, counting from zero
+    #[structopt(long = "nth-arrangement")]
+    nth: Option<u128>,
+
+    /// Show a random day 10 arrangement, chosen using this seed
+    #[structopt(long = "random-arrangement")]
+    random: Option<u64>,
+}
+
+// The outlet, every adapter in order, and the device.
+struct Chain {
+    joltages: Vec<usize>,
+    rules: JoltageRules,
+}
+
+impl Chain {
+    fn new(mut adapters: Vec<usize>, rules: JoltageRules) -> Self {
+        adapters.sort_unstable();
+        // With no adapters, the device plugs straight into the outlet.
+        let device = adapters.last().copied().unwrap_or(0) + rules.device_offset;
+        let mut joltages = vec![0];
+        joltages.extend(adapters);
+        joltages.push(device);
+        Self { joltages, rules }
+    }
+
+    // How often each difference appears when using every adapter.
+    fn differences(&self) -> Result<BTreeMap<usize, usize>, ChainError> {
+        let mut counts = BTreeMap::new();
+        for pair in self.joltages.windows(2) {
+            let gap = pair[1] - pair[0];
+            if !self.rules.gaps.contains(&gap) {
+                return Err(ChainError::Gap {
+                    from: pair[0],
+                    to: pair[1],
+                });
+            }
+            *counts.entry(gap).or_insert(0) += 1;
+        }
+        Ok(counts)
+    }
+
+    // Positions of the joltages that can follow the one at position from.
+    fn next_steps(&self, from: usize) -> impl Iterator<Item = usize> + '_ {
+        let max_gap = self.rules.gaps.iter().max().copied().unwrap_or(0);
+        let start = self.joltages[from];
+        (from + 1..self.joltages.len())
+            .take_while(move |&to| self.joltages[to] - start <= max_gap)
+            .filter(move |&to| self.rules.gaps.contains(&(self.joltages[to] - start)))
+    }
+
+    // paths[i] is the number of ways to get from the i'th joltage to the device.
+    fn paths(&self) -> Result<Vec<u128>, ChainError> {
+        let count = self.joltages.len();
+        let mut paths = vec![0u128; count];
+        paths[count - 1] = 1;
+        for from in (0..count - 1).rev() {
+            paths[from] = self
+                .next_steps(from)
+                .try_fold(0u128, |total, to| total.checked_add(paths[to]))
+                .ok_or(ChainError::Overflow)?;
+        }
+        Ok(paths)
+    }
+
+    // Arrangements are listed by the joltages that they pass through after the outlet, ending with
+    // the device.  Counting the paths through each choice lets us skip straight to the k'th.
+    fn nth_arrangement(&self, paths: &[u128], mut k: u128) -> Option<Vec<usize>> {
+        if k >= paths[0] {
+            return None;
+        }
+        let device = self.joltages.len() - 1;
+        let mut arrangement = vec![];
+        let mut position = 0;
+        while position != device {
+            for to in self.next_steps(position) {
+                if k < paths[to] {
+                    position = to;
+                    break;
+                }
+                k -= paths[to];
+            }
+            arrangement.push(self.joltages[position]);
+        }
+        Some(arrangement)
+    }
+
+    // Every arrangement, in lexicographic order, produced only as needed.
+    fn iter_arrangements(&self) -> Result<impl Iterator<Item = Vec<usize>> + '_, ChainError> {
+        let paths = self.paths()?;
+        let iter = (0..paths[0]).map(move |k| self.nth_arrangement(&paths, k).unwrap());
+        Ok(iter)
+    }
+
+    // Choosing k uniformly makes every arrangement equally likely.
+    fn random_arrangement(&self, seed: u64) -> Result<Vec<usize>, ChainError> {
+        let paths = self.paths()?;
+        if paths[0] == 0 {
+            return Err(ChainError::NoArrangement);
+        }
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let k = rng.gen_range(0..paths[0]);
+        Ok(self.nth_arrangement(&paths, k).unwrap())
+    }
+
+    fn arrangements(&self) -> Result<u128, ChainError> {
+        match self.paths()?[0] {
+            0 => Err(ChainError::NoArrangement),
+            arrangements => Ok(arrangements),
+        }
+    }
+}
+
+fn format_arrangement(arrangement: &[usize]) -> String {
+    let joltages: Vec<String> = arrangement.iter().map(|j| j.to_string()).collect();
+    joltages.join(",")
+}
+
+pub(crate) fn day10(gaps: &[usize], device_offset: Option<usize>, show: &ShowArrangements) {
     let input = std::fs::read_to_string("data/day10.txt").unwrap();
-    let mut numbers: Vec<usize> = input.lines().map(|line| line.parse().unwrap()).collect();
-
-    numbers.sort_unstable();
-
-    let (ones, threes) =
-        numbers
-            .windows(2)
-            .fold((1, 1), |(os, ts), pair| match pair[1] - pair[0] {
-                1 => (os + 1, ts),
-                3 => (os, ts + 1),
-                _ => (os, ts),
-            });
-    println!("Part one answer is {}", ones * threes);
-
-    let &max = numbers.last().unwrap();
-    let builtin_adapter = max + 3;
-    let mut paths = vec![0u64; builtin_adapter + 1];
-    paths[builtin_adapter] = 1;
-    for &adapter in numbers.iter().rev() {
-        paths[adapter] = paths[adapter + 1] + paths[adapter + 2] + paths[adapter + 3];
-    }
-    println!("Part two answer is {}", paths[1] + paths[2] + paths[3]);
+    let numbers: Vec<usize> = input.lines().map(|line| line.parse().unwrap()).collect();
+
+    let mut rules = JoltageRules::default();
+    if !gaps.is_empty() {
+        rules.gaps = gaps.to_vec();
+    }
+    if let Some(offset) = device_offset {
+        rules.device_offset = offset;
+    }
+    let chain = Chain::new(numbers, rules);
+
+    match chain.differences() {
+        Ok(counts) => {
+            let ones = counts.get(&1).unwrap_or(&0);
+            let threes = counts.get(&3).unwrap_or(&0);
+            println!("Part one answer is {}", ones * threes);
+        }
+        Err(err) => println!("Part one has no answer: {}", err),
+    }
+
+    match chain.arrangements() {
+        Ok(arrangements) => println!("Part two answer is {}", arrangements),
+        Err(err) => println!("Part two has no answer: {}", err),
+    }
+
+    if let Some(count) = show.list {
+        match chain.iter_arrangements() {
+            Ok(arrangements) => {
+                for arrangement in arrangements.take(count) {
+                    println!("{}", format_arrangement(&arrangement));
+                }
+            }
+            Err(err) => println!("No arrangements: {}", err),
+        }
+    }
+
+    if let Some(k) = show.nth {
+        let arrangement = chain.paths().map(|paths| chain.nth_arrangement(&paths, k));
+        match arrangement {
+            Ok(Some(arrangement)) => {
+                println!("Arrangement {} is {}", k, format_arrangement(&arrangement))
+            }
+            Ok(None) => println!("There is no arrangement {}", k),
+            Err(err) => println!("No arrangements: {}", err),
+        }
+    }
+
+    if let Some(seed) = show.random {
+        match chain.random_arrangement(seed) {
+            Ok(arrangement) => {
+                println!("Random arrangement is {}", format_arrangement(&arrangement))
+            }
+            Err(err) => println!("No arrangements: {}", err),
+        }
+    }
 }
@@ -1,7 +1,14 @@
 use std::convert::TryInto;
 use std::fmt;
 use std::fmt::Display;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
 use std::iter::successors;
+use std::path::PathBuf;
+use std::str::FromStr;
+use structopt::StructOpt;
 
 const DIRECTIONS: &[(isize, isize)] = &[
     (-1, -1),
@@ -38,9 +45,6 @@ impl Tile {
     }
 }
 
-#[derive(Debug)]
-struct ParseTileError;
-
 impl From<char> for Tile {
     fn from(c: char) -> Self {
         match c {
@@ -76,15 +80,16 @@ fn neighbour(
     }
 }
 
+// Tiles are stored row by row in a single vector.
 struct Layout {
-    tiles: Vec<Vec<Tile>>,
+    tiles: Vec<Tile>,
     columns: usize,
     rows: usize,
 }
 
 impl Display for Layout {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for row in &self.tiles {
+        for row in self.tiles.chunks(self.columns) {
             for tile in row {
                 write!(f, "{}", tile)?;
             }
@@ -99,19 +104,14 @@ impl Layout {
         let columns = tiles[0].len();
         let rows = tiles.len();
         Self {
-            tiles,
+            tiles: tiles.into_iter().flatten().collect(),
             columns,
             rows,
         }
     }
 
-    fn occupied_neighbours(&self, row: usize, col: usize) -> usize {
-        let bounds = (self.rows, self.columns);
-        DIRECTIONS
-            .iter()
-            .filter_map(|&step| neighbour((row, col), step, bounds))
-            .filter(|&(r, c)| self.tiles[r][c].occupied())
-            .count()
+    fn index(&self, (row, col): (usize, usize)) -> usize {
+        row * self.columns + col
     }
 
     fn line(
@@ -123,101 +123,646 @@ impl Layout {
         successors(Some(start), move |&s| neighbour(s, step, bounds)).skip(1)
     }
 
-    fn occupied_visible(&self, row: usize, col: usize) -> usize {
+    // The seats that are next to the given one.
+    fn adjacent_seats(&self, position: (usize, usize)) -> Vec<usize> {
+        let bounds = (self.rows, self.columns);
+        DIRECTIONS
+            .iter()
+            .filter_map(|&step| neighbour(position, step, bounds))
+            .map(|p| self.index(p))
+            .filter(|&i| self.tiles[i].is_seat())
+            .collect()
+    }
+
+    // The first seat that can be seen in each direction from the given one, looking no further
+    // than max_distance tiles if there is a limit.
+    fn visible_seats(&self, position: (usize, usize), max_distance: Option<usize>) -> Vec<usize> {
         DIRECTIONS
             .iter()
-            .filter(|&&step| {
-                self.line((row, col), step)
-                    .find(|&(r, c)| self.tiles[r][c].is_seat())
-                    .map(|(r, c)| self.tiles[r][c].occupied())
-                    .unwrap_or(false)
+            .filter_map(|&step| {
+                self.line(position, step)
+                    .take(max_distance.unwrap_or(usize::MAX))
+                    .map(|p| self.index(p))
+                    .find(|&i| self.tiles[i].is_seat())
             })
+            .collect()
+    }
+
+    fn occupied_count(&self) -> usize {
+        self.tiles.iter().filter(|tile| tile.occupied()).count()
+    }
+}
+
+// Each seat's neighbours, worked out once.  The neighbours of the tile at index i are
+// seats[starts[i]..starts[i + 1]]; floor tiles have none.
+struct Neighbours {
+    starts: Vec<usize>,
+    seats: Vec<usize>,
+}
+
+impl Neighbours {
+    fn new(layout: &Layout, seats_of: impl Fn(&Layout, (usize, usize)) -> Vec<usize>) -> Self {
+        let mut starts = vec![0];
+        let mut seats = vec![];
+        for index in 0..layout.tiles.len() {
+            if layout.tiles[index].is_seat() {
+                let position = (index / layout.columns, index % layout.columns);
+                seats.extend(seats_of(layout, position));
+            }
+            starts.push(seats.len());
+        }
+        Self { starts, seats }
+    }
+
+    fn for_rules(layout: &Layout, neighbourhood: Neighbourhood) -> Self {
+        match neighbourhood {
+            Neighbourhood::Adjacent => Self::new(layout, Layout::adjacent_seats),
+            Neighbourhood::LineOfSight { max_distance } => Self::new(layout, |layout, position| {
+                layout.visible_seats(position, max_distance)
+            }),
+        }
+    }
+
+    fn of(&self, index: usize) -> &[usize] {
+        &self.seats[self.starts[index]..self.starts[index + 1]]
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+enum Neighbourhood {
+    Adjacent,
+    LineOfSight { max_distance: Option<usize> },
+}
+
+impl FromStr for Neighbourhood {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "adjacent" => Ok(Self::Adjacent),
+            "sight" => Ok(Self::LineOfSight { max_distance: None }),
+            _ => Err(format!("unknown neighbourhood {}", s)),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct SeatingRules {
+    neighbourhood: Neighbourhood,
+
+    // An occupied seat is left when at least this many of its neighbours are occupied.
+    leave_threshold: usize,
+
+    // An empty seat is taken when at most this many of its neighbours are occupied.
+    occupy_threshold: usize,
+}
+
+impl SeatingRules {
+    fn part_one() -> Self {
+        Self {
+            neighbourhood: Neighbourhood::Adjacent,
+            leave_threshold: 4,
+            occupy_threshold: 0,
+        }
+    }
+
+    fn part_two() -> Self {
+        Self {
+            neighbourhood: Neighbourhood::LineOfSight { max_distance: None },
+            leave_threshold: 5,
+            occupy_threshold: 0,
+        }
+    }
+}
+
+// Seating rules to try besides the two parts.
+#[derive(StructOpt)]
+pub(crate) struct CustomSeating {
+    /// Also run day 11 with this neighbourhood: adjacent or sight
+    #[structopt(long)]
+    neighbourhood: Option<Neighbourhood>,
+
+    /// How far a day 11 passenger can see
+    #[structopt(long)]
+    max_distance: Option<usize>,
+
+    /// How many occupied neighbours make a day 11 passenger leave
+    #[structopt(long, default_value = "4")]
+    leave: usize,
+
+    /// How many occupied neighbours a day 11 passenger will put up with when sitting down
+    #[structopt(long, default_value = "0")]
+    occupy: usize,
+}
+
+impl CustomSeating {
+    // A distance only makes sense when passengers look along lines of sight.
+    fn rules(&self) -> Result<Option<SeatingRules>, String> {
+        let neighbourhood = match (self.neighbourhood, self.max_distance) {
+            (None, None) => return Ok(None),
+            (Some(Neighbourhood::LineOfSight { .. }), max_distance) => {
+                Neighbourhood::LineOfSight { max_distance }
+            }
+            (Some(Neighbourhood::Adjacent), None) => Neighbourhood::Adjacent,
+            (_, Some(_)) => {
+                return Err("--max-distance needs --neighbourhood sight".to_owned());
+            }
+        };
+        Ok(Some(SeatingRules {
+            neighbourhood,
+            leave_threshold: self.leave,
+            occupy_threshold: self.occupy,
+        }))
+    }
+}
+
+// How a simulation ended.  Generations are counted from the starting layout, which is
+// generation 0.
+#[derive(Debug)]
+enum Outcome {
+    Stable { generation: usize },
+    Oscillating { start: usize, period: usize },
+}
+
+impl Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Stable { generation } => write!(f, "stable from generation {}", generation),
+            Self::Oscillating { start, period } => write!(
+                f,
+                "repeats every {} generations from generation {}",
+                period, start
+            ),
+        }
+    }
+}
+
+// A key for each tile, so that the layout can be hashed by xor-ing the keys of the occupied
+// seats and kept up to date one change at a time.
+fn tile_key(index: usize) -> u64 {
+    let mut z = (index as u64)
+        .wrapping_add(1)
+        .wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+// Runs the seating rules over a layout.  The next generation is written into a second buffer
+// which is then swapped in, and only seats next to one that just changed are looked at again.
+struct Simulation {
+    layout: Layout,
+    next: Vec<Tile>,
+    initial: Vec<Tile>,
+    neighbours: Neighbours,
+    rules: SeatingRules,
+    generation: usize,
+    hash: u64,
+
+    // Seats to look at in the next generation, and which seats are already in that list.
+    pending: Vec<usize>,
+    queued: Vec<bool>,
+    changed: Vec<usize>,
+}
+
+impl Simulation {
+    fn new(layout: Layout, rules: SeatingRules) -> Self {
+        let neighbours = Neighbours::for_rules(&layout, rules.neighbourhood);
+        let hash = (0..layout.tiles.len())
+            .filter(|&i| layout.tiles[i].occupied())
+            .fold(0, |hash, i| hash ^ tile_key(i));
+        let pending: Vec<usize> = (0..layout.tiles.len())
+            .filter(|&i| layout.tiles[i].is_seat())
+            .collect();
+        let mut queued = vec![false; layout.tiles.len()];
+        for &index in &pending {
+            queued[index] = true;
+        }
+        Self {
+            next: layout.tiles.clone(),
+            initial: layout.tiles.clone(),
+            layout,
+            neighbours,
+            rules,
+            generation: 0,
+            hash,
+            pending,
+            queued,
+            changed: vec![],
+        }
+    }
+
+    fn occupied_neighbours(&self, index: usize) -> usize {
+        self.neighbours
+            .of(index)
+            .iter()
+            .filter(|&&i| self.layout.tiles[i].occupied())
             .count()
     }
 
+    // Returns whether any seat changed.
     fn step(&mut self) -> bool {
-        let mut changed = false;
-        let mut new_tiles: Vec<Vec<Tile>> = self.tiles.clone();
-        for (row, new_row) in new_tiles.iter_mut().enumerate() {
-            for (col, new_tile) in new_row.iter_mut().enumerate() {
-                if !self.tiles[row][col].is_seat() {
-                    continue;
-                }
-                let occupied_neighbours = self.occupied_neighbours(row, col);
-                match self.tiles[row][col].occupied() {
-                    true => {
-                        if occupied_neighbours >= 4 {
-                            *new_tile = Tile::Seat(false);
-                            changed = true;
-                        }
-                    }
-                    false => {
-                        if occupied_neighbours == 0 {
-                            *new_tile = Tile::Seat(true);
-                            changed = true;
-                        }
-                    }
+        self.changed.clear();
+        for &index in &self.pending {
+            self.queued[index] = false;
+            let occupied = self.layout.tiles[index].occupied();
+            let occupied_neighbours = self.occupied_neighbours(index);
+            let flips = if occupied {
+                occupied_neighbours >= self.rules.leave_threshold
+            } else {
+                occupied_neighbours <= self.rules.occupy_threshold
+            };
+            if flips {
+                self.next[index] = Tile::Seat(!occupied);
+                self.changed.push(index);
+                self.hash ^= tile_key(index);
+            }
+        }
+
+        // Bring the old buffer up to date so that both match before the next step.
+        std::mem::swap(&mut self.layout.tiles, &mut self.next);
+        for &index in &self.changed {
+            self.next[index] = self.layout.tiles[index];
+        }
+
+        // Seeing a seat is mutual, so the seats that might change next are the ones that just
+        // did, which can flip straight back under some thresholds, and their neighbours.
+        self.pending.clear();
+        for &index in &self.changed {
+            let seats = std::iter::once(&index).chain(self.neighbours.of(index));
+            for &seat in seats {
+                if !self.queued[seat] {
+                    self.queued[seat] = true;
+                    self.pending.push(seat);
                 }
             }
         }
-        self.tiles = new_tiles;
-        changed
+        self.generation += 1;
+        !self.changed.is_empty()
+    }
+
+    // Steps until nothing changes or an earlier layout comes round again.  Uses Brent's method,
+    // keeping a single earlier layout which is replaced whenever the generation count reaches a
+    // power of two, so only one copy is needed however long the cycle is.
+    // The observer sees the starting layout and then every generation that differs from the
+    // one before it.
+    fn run<E>(&mut self, mut observe: impl FnMut(&Self) -> Result<(), E>) -> Result<Outcome, E> {
+        observe(self)?;
+        let mut saved = (self.generation, self.hash, self.layout.tiles.clone());
+        loop {
+            if !self.step() {
+                return Ok(Outcome::Stable {
+                    generation: self.generation - 1,
+                });
+            }
+            observe(self)?;
+
+            let (saved_generation, saved_hash, ref saved_tiles) = saved;
+            if self.hash == saved_hash && self.matches(saved_tiles) {
+                let period = self.generation - saved_generation;
+                return Ok(Outcome::Oscillating {
+                    start: self.cycle_start(period),
+                    period,
+                });
+            }
+            if self.generation.is_power_of_two() {
+                saved = (self.generation, self.hash, self.layout.tiles.clone());
+            }
+        }
+    }
+
+    fn matches(&self, tiles: &[Tile]) -> bool {
+        self.layout
+            .tiles
+            .iter()
+            .zip(tiles)
+            .all(|(a, b)| a.occupied() == b.occupied())
+    }
+
+    // The first generation of a cycle, found by running two copies from the start that are a
+    // period apart until they agree.
+    fn cycle_start(&self, period: usize) -> usize {
+        let mut lead = self.restarted();
+        for _ in 0..period {
+            lead.step();
+        }
+        let mut trail = self.restarted();
+        while !(lead.hash == trail.hash && lead.matches(&trail.layout.tiles)) {
+            lead.step();
+            trail.step();
+        }
+        trail.generation
+    }
+
+    fn restarted(&self) -> Self {
+        Self::new(
+            Layout {
+                tiles: self.initial.clone(),
+                columns: self.layout.columns,
+                rows: self.layout.rows,
+            },
+            self.rules,
+        )
+    }
+}
+
+#[derive(Copy, Clone)]
+enum FrameFormat {
+    Ppm,
+    Pgm,
+    Text,
+}
+
+impl FromStr for FrameFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ppm" => Ok(Self::Ppm),
+            "pgm" => Ok(Self::Pgm),
+            "text" => Ok(Self::Text),
+            _ => Err(format!("unknown frame format {}", s)),
+        }
+    }
+}
+
+// Where to save the frames of each day 11 simulation.
+#[derive(StructOpt)]
+pub(crate) struct Frames {
+    /// Save every day 11 generation in this directory, with a subdirectory for each simulation
+    #[structopt(long)]
+    frames: Option<PathBuf>,
+
+    /// How to save day 11 frames: ppm, pgm or text
+    #[structopt(long, default_value = "ppm")]
+    frame_format: FrameFormat,
+
+    /// How many pixels across each day 11 tile is in image frames
+    #[structopt(long, default_value = "4")]
+    tile_size: usize,
+}
+
+// How a tile looks in a frame.
+#[derive(Copy, Clone)]
+enum Shade {
+    Floor,
+    Empty,
+    Occupied,
+    Emptied,
+    Filled,
+}
+
+impl Shade {
+    fn rgb(self) -> [u8; 3] {
+        match self {
+            Self::Floor => [40, 40, 40],
+            Self::Empty => [70, 130, 180],
+            Self::Occupied => [200, 50, 50],
+            Self::Emptied => [150, 230, 255],
+            Self::Filled => [255, 210, 0],
+        }
+    }
+
+    fn grey(self) -> u8 {
+        match self {
+            Self::Floor => 0,
+            Self::Empty => 90,
+            Self::Occupied => 170,
+            Self::Emptied | Self::Filled => 255,
+        }
+    }
+
+    fn ansi(self) -> &'static str {
+        match self {
+            Self::Floor => "\x1b[90m",
+            Self::Empty => "\x1b[34m",
+            Self::Occupied => "\x1b[31m",
+            Self::Emptied => "\x1b[1;36m",
+            Self::Filled => "\x1b[1;33m",
+        }
+    }
+}
+
+// Writes numbered image files, or appends to a single text file, one frame per generation.
+struct FrameWriter {
+    directory: PathBuf,
+    format: FrameFormat,
+    tile_size: usize,
+    text: Option<BufWriter<File>>,
+    changed: Vec<bool>,
+}
+
+impl FrameWriter {
+    fn new(directory: PathBuf, format: FrameFormat, tile_size: usize) -> io::Result<Self> {
+        fs::create_dir_all(&directory)?;
+        let text = match format {
+            FrameFormat::Text => {
+                let file = File::create(directory.join("frames.txt"))?;
+                Some(BufWriter::new(file))
+            }
+            _ => None,
+        };
+        Ok(Self {
+            directory,
+            format,
+            tile_size,
+            text,
+            changed: vec![],
+        })
+    }
+
+    fn shades(&mut self, simulation: &Simulation) -> Vec<Shade> {
+        let tiles = &simulation.layout.tiles;
+        self.changed.clear();
+        self.changed.resize(tiles.len(), false);
+        if simulation.generation > 0 {
+            for &index in &simulation.changed {
+                self.changed[index] = true;
+            }
+        }
+        tiles
+            .iter()
+            .zip(&self.changed)
+            .map(|(tile, &changed)| match (tile, changed) {
+                (Tile::Floor, _) => Shade::Floor,
+                (Tile::Seat(false), false) => Shade::Empty,
+                (Tile::Seat(true), false) => Shade::Occupied,
+                (Tile::Seat(false), true) => Shade::Emptied,
+                (Tile::Seat(true), true) => Shade::Filled,
+            })
+            .collect()
     }
 
-    fn step2(&mut self) -> bool {
-        let mut changed = false;
-        let mut new_tiles: Vec<Vec<Tile>> = self.tiles.clone();
-        for (row, new_row) in new_tiles.iter_mut().enumerate() {
-            for (col, new_tile) in new_row.iter_mut().enumerate() {
-                if !self.tiles[row][col].is_seat() {
-                    continue;
+    fn write(&mut self, simulation: &Simulation) -> io::Result<()> {
+        let shades = self.shades(simulation);
+        let layout = &simulation.layout;
+        let generation = simulation.generation;
+        match self.format {
+            FrameFormat::Text => {
+                let text = self.text.as_mut().unwrap();
+                writeln!(text, "Generation {}", generation)?;
+                for (row, tiles) in shades
+                    .chunks(layout.columns)
+                    .zip(layout.tiles.chunks(layout.columns))
+                {
+                    for (shade, tile) in row.iter().zip(tiles) {
+                        write!(text, "{}{}", shade.ansi(), tile)?;
+                    }
+                    writeln!(text, "\x1b[0m")?;
                 }
-                let occupied_visible = self.occupied_visible(row, col);
-                match self.tiles[row][col].occupied() {
-                    true => {
-                        if occupied_visible >= 5 {
-                            *new_tile = Tile::Seat(false);
-                            changed = true;
+                writeln!(text)
+            }
+            FrameFormat::Ppm | FrameFormat::Pgm => {
+                let (magic, extension) = match self.format {
+                    FrameFormat::Ppm => ("P6", "ppm"),
+                    _ => ("P5", "pgm"),
+                };
+                let name = format!("frame{:05}.{}", generation, extension);
+                let mut image = BufWriter::new(File::create(self.directory.join(name))?);
+                let size = self.tile_size;
+                writeln!(image, "{}", magic)?;
+                writeln!(image, "{} {}", layout.columns * size, layout.rows * size)?;
+                writeln!(image, "255")?;
+                for row in shades.chunks(layout.columns) {
+                    let mut line = vec![];
+                    for shade in row {
+                        for _ in 0..size {
+                            match self.format {
+                                FrameFormat::Ppm => line.extend(&shade.rgb()),
+                                _ => line.push(shade.grey()),
+                            }
                         }
                     }
-                    false => {
-                        if occupied_visible == 0 {
-                            *new_tile = Tile::Seat(true);
-                            changed = true;
-                        }
+                    for _ in 0..size {
+                        image.write_all(&line)?;
                     }
                 }
+                image.flush()
             }
         }
-        self.tiles = new_tiles;
-        changed
     }
 
-    fn occupied_count(&self) -> u32 {
-        let mut count = 0;
-        for row in 0..self.rows {
-            for col in 0..self.columns {
-                if self.tiles[row][col].occupied() {
-                    count += 1;
-                }
-            }
+    fn finish(self) -> io::Result<()> {
+        match self.text {
+            Some(mut text) => text.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+fn report(part: &str, layout: Layout, rules: SeatingRules, frames: &Frames, name: &str) {
+    let mut simulation = Simulation::new(layout, rules);
+    let outcome = match &frames.frames {
+        Some(directory) => {
+            FrameWriter::new(directory.join(name), frames.frame_format, frames.tile_size).and_then(
+                |mut writer| {
+                    let outcome = simulation.run(|simulation| writer.write(simulation))?;
+                    writer.finish()?;
+                    Ok(outcome)
+                },
+            )
         }
-        count
+        None => simulation.run(|_| Ok(())),
+    };
+    match outcome {
+        Ok(Outcome::Stable { generation }) => println!(
+            "{} answer is {} (stable after {} generations)",
+            part,
+            simulation.layout.occupied_count(),
+            generation
+        ),
+        Ok(outcome) => println!("{} has no answer: seating {}", part, outcome),
+        Err(err) => println!("{} frames couldn't be saved: {}", part, err),
     }
 }
 
-pub(crate) fn day11() {
+pub(crate) fn day11(custom: &CustomSeating, frames: &Frames) {
     let input = std::fs::read_to_string("data/day11.txt").unwrap();
     let tiles: Vec<Vec<Tile>> = input
         .lines()
         .map(|line| line.chars().map(Tile::from).collect())
         .collect();
 
-    let mut layout = Layout::new(tiles.clone());
-    while layout.step() {}
-    println!("Part one answer is {}", layout.occupied_count());
+    let part_one = SeatingRules::part_one();
+    report(
+        "Part one",
+        Layout::new(tiles.clone()),
+        part_one,
+        frames,
+        "part-one",
+    );
+    let part_two = SeatingRules::part_two();
+    report(
+        "Part two",
+        Layout::new(tiles.clone()),
+        part_two,
+        frames,
+        "part-two",
+    );
+    match custom.rules() {
+        Ok(Some(rules)) => report(
+            "Custom seating",
+            Layout::new(tiles),
+            rules,
+            frames,
+            "custom",
+        ),
+        Ok(None) => {}
+        Err(err) => println!("Bad custom seating: {}", err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(layout: &str, rules: SeatingRules) -> Outcome {
+        let tiles = layout
+            .lines()
+            .map(|line| line.chars().map(Tile::from).collect())
+            .collect();
+        Simulation::new(Layout::new(tiles), rules)
+            .run(|_| Ok::<(), ()>(()))
+            .unwrap()
+    }
 
-    let mut layout = Layout::new(tiles);
-    while layout.step2() {}
-    println!("Part two answer is {}", layout.occupied_count());
+    fn adjacent(leave_threshold: usize, occupy_threshold: usize) -> SeatingRules {
+        SeatingRules {
+            neighbourhood: Neighbourhood::Adjacent,
+            leave_threshold,
+            occupy_threshold,
+        }
+    }
+
+    #[test]
+    fn lone_seat_flips_forever() {
+        let outcome = run("L", adjacent(0, 0));
+        assert!(matches!(
+            outcome,
+            Outcome::Oscillating {
+                start: 0,
+                period: 2
+            }
+        ));
+    }
+
+    #[test]
+    fn seats_without_neighbours_flip_forever() {
+        let outcome = run("L.L", adjacent(0, 0));
+        assert!(matches!(
+            outcome,
+            Outcome::Oscillating {
+                start: 0,
+                period: 2
+            }
+        ));
+    }
+
+    #[test]
+    fn part_one_rules_settle() {
+        let layout = "L.LL.LL.LL\nLLLLLLL.LL\nL.L.L..L..\nLLLL.LL.LL\nL.LL.LL.LL\n\
+                      L.LLLLL.LL\n..L.L.....\nLLLLLLLLLL\nL.LLLLLL.L\nL.LLLLL.LL";
+        let outcome = run(layout, SeatingRules::part_one());
+        assert!(matches!(outcome, Outcome::Stable { generation: 5 }));
+    }
 }
@@ -1,4 +1,9 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fmt::Display;
+use std::path::PathBuf;
 use std::str::FromStr;
+use structopt::StructOpt;
 
 #[derive(Copy, Clone)]
 enum Direction {
@@ -9,7 +14,7 @@ enum Direction {
 }
 
 impl Direction {
-    fn to_degrees(&self) -> isize {
+    fn to_degrees(self) -> isize {
         match self {
             Self::North => 0,
             Self::East => 90,
@@ -17,21 +22,55 @@ impl Direction {
             Self::West => 270,
         }
     }
+}
+
+// How to handle turns that aren't a multiple of 90 degrees.
+#[derive(Copy, Clone)]
+enum Turns {
+    // Only allow turns that keep positions exact.
+    Exact,
+
+    // Allow any turn, rounding each rotated offset to the nearest whole unit, with halves rounded
+    // away from zero.
+    Rounded,
+}
 
-    fn from_degrees(degrees: isize) -> Self {
-        match degrees {
-            0 => Self::North,
-            90 => Self::East,
-            180 => Self::South,
-            270 => Self::West,
-            _ => panic!("Invalid bearing!"),
+#[derive(Debug)]
+enum NavigationError {
+    InexactTurn(isize),
+}
+
+impl Display for NavigationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InexactTurn(degrees) => {
+                write!(f, "turning by {} degrees isn't exact", degrees)
+            }
         }
     }
+}
+
+// Degrees clockwise from north, always in 0..360.
+#[derive(Copy, Clone)]
+struct Heading {
+    degrees: isize,
+}
 
-    fn add_degrees(&self, turn: isize) -> Self {
-        let start = self.to_degrees();
-        let end = (start + turn + 360) % 360;
-        Self::from_degrees(end)
+impl Heading {
+    fn new(degrees: isize) -> Self {
+        Self {
+            degrees: degrees.rem_euclid(360),
+        }
+    }
+
+    fn turn(self, degrees: isize) -> Self {
+        Self::new(self.degrees + degrees)
+    }
+}
+
+impl From<Direction> for Heading {
+    fn from(direction: Direction) -> Self {
+        Self::new(direction.to_degrees())
     }
 }
 
@@ -70,7 +109,7 @@ impl FromStr for Instruction {
     }
 }
 
-#[derive(Default)]
+#[derive(Copy, Clone, Default, PartialEq)]
 struct Position {
     east: isize,
     north: isize,
@@ -99,83 +138,458 @@ impl Position {
         self.step(east, north)
     }
 
-    fn rotate(&self, amount: isize) -> Self {
-        match amount {
-            0 => Self::new(self.east, self.north),
-            90 => Self::new(self.north, -self.east),
-            180 => Self::new(-self.east, -self.north),
-            270 => Self::new(-self.north, self.east),
-            _ => panic!("bad turn {}", amount),
+    // Rotates clockwise about the origin.
+    fn rotate(&self, amount: isize, turns: Turns) -> Result<Self, NavigationError> {
+        let rotated = match (amount.rem_euclid(360), turns) {
+            (0, _) => Self::new(self.east, self.north),
+            (90, _) => Self::new(self.north, -self.east),
+            (180, _) => Self::new(-self.east, -self.north),
+            (270, _) => Self::new(-self.north, self.east),
+            (_, Turns::Exact) => return Err(NavigationError::InexactTurn(amount)),
+            (degrees, Turns::Rounded) => {
+                let (sin, cos) = (degrees as f64).to_radians().sin_cos();
+                let (east, north) = (self.east as f64, self.north as f64);
+                let new_east = east * cos + north * sin;
+                let new_north = north * cos - east * sin;
+                Self::new(new_east.round() as isize, new_north.round() as isize)
+            }
+        };
+        Ok(rotated)
+    }
+
+    fn move_along(
+        &self,
+        heading: Heading,
+        amount: isize,
+        turns: Turns,
+    ) -> Result<Self, NavigationError> {
+        let offset = Self::new(0, amount).rotate(heading.degrees, turns)?;
+        Ok(self.step(offset.east, offset.north))
+    }
+}
+
+// Where the ship was after each instruction, starting from where it set off, along with the
+// waypoint when it was steering by one.
+#[derive(Default)]
+struct Track {
+    points: Vec<(Position, Option<Position>)>,
+}
+
+impl Track {
+    fn record(&mut self, position: Position, waypoint: Option<Position>) {
+        self.points.push((position, waypoint));
+    }
+
+    fn positions(&self) -> impl Iterator<Item = Position> + '_ {
+        self.points.iter().map(|&(position, _waypoint)| position)
+    }
+
+    // Straight line distance, summed over every leg.
+    fn distance(&self) -> f64 {
+        let positions: Vec<Position> = self.positions().collect();
+        positions
+            .windows(2)
+            .map(|leg| {
+                let east = (leg[1].east - leg[0].east) as f64;
+                let north = (leg[1].north - leg[0].north) as f64;
+                east.hypot(north)
+            })
+            .sum()
+    }
+
+    // The south-west and north-east corners of the smallest box around the track.
+    fn bounds(&self) -> (Position, Position) {
+        let mut positions = self.positions();
+        let first = positions.next().unwrap_or_default();
+        positions.fold((first, first), |(min, max), p| {
+            let min = Position::new(min.east.min(p.east), min.north.min(p.north));
+            let max = Position::new(max.east.max(p.east), max.north.max(p.north));
+            (min, max)
+        })
+    }
+
+    // Farthest by Manhattan distance, the earliest one if there's a tie.
+    fn farthest(&self) -> Position {
+        self.positions().fold(Position::default(), |farthest, p| {
+            if p.manhattan() > farthest.manhattan() {
+                p
+            } else {
+                farthest
+            }
+        })
+    }
+
+    fn print_stats(&self) {
+        let (min, max) = self.bounds();
+        let farthest = self.farthest();
+        println!("  distance travelled: {:.2}", self.distance());
+        println!(
+            "  bounding box: {},{} to {},{}",
+            min.east, min.north, max.east, max.north
+        );
+        println!(
+            "  farthest point: {},{} ({} away)",
+            farthest.east,
+            farthest.north,
+            farthest.manhattan()
+        );
+    }
+
+    // North is up, so norths are negated to give SVG coordinates.
+    fn to_svg(&self) -> String {
+        let (min, max) = self.bounds();
+        let margin = ((max.east - min.east).max(max.north - min.north) / 20).max(1);
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\">\n",
+            min.east - margin,
+            -max.north - margin,
+            max.east - min.east + 2 * margin,
+            max.north - min.north + 2 * margin
+        );
+        for &(position, waypoint) in &self.points {
+            if let Some(waypoint) = waypoint {
+                svg += &format!(
+                    "  <line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"orange\" \
+                     vector-effect=\"non-scaling-stroke\"/>\n",
+                    position.east,
+                    -position.north,
+                    position.east + waypoint.east,
+                    -(position.north + waypoint.north)
+                );
+            }
         }
+        let points: Vec<String> = self
+            .positions()
+            .map(|p| format!("{},{}", p.east, -p.north))
+            .collect();
+        svg += &format!(
+            "  <polyline points=\"{}\" fill=\"none\" stroke=\"navy\" \
+             vector-effect=\"non-scaling-stroke\"/>\n",
+            points.join(" ")
+        );
+        svg += &format!(
+            "  <circle cx=\"0\" cy=\"0\" r=\"{}\" fill=\"green\"/>\n",
+            margin / 2 + 1
+        );
+        svg += "</svg>\n";
+        svg
     }
 }
 
 struct Ship {
     position: Position,
-    direction: Direction,
+    heading: Heading,
     waypoint: Position,
+    turns: Turns,
+    track: Track,
 }
 
 impl Ship {
-    fn new(position: Position, direction: Direction, waypoint: Position) -> Self {
+    fn new(position: Position, heading: Heading, waypoint: Position, turns: Turns) -> Self {
         Self {
             position,
-            direction,
+            heading,
             waypoint,
+            turns,
+            track: Track::default(),
+        }
+    }
+
+    // Follows every instruction using the model, recording the track as it goes.
+    fn navigate(
+        &mut self,
+        model: &dyn NavigationModel,
+        instructions: &[Instruction],
+    ) -> Result<(), NavigationError> {
+        model.start(self)?;
+        self.track.record(self.position, model.waypoint(self));
+        for instruction in instructions {
+            model.act(self, instruction)?;
+            self.track.record(self.position, model.waypoint(self));
         }
+        Ok(())
     }
 
-    fn act(&mut self, instruction: &Instruction) {
+    // The heading can point anywhere, but in exact mode it has to stay on a cardinal direction.
+    fn turn(&self, degrees: isize) -> Result<Heading, NavigationError> {
+        match self.turns {
+            Turns::Exact if degrees % 90 != 0 => Err(NavigationError::InexactTurn(degrees)),
+            _ => Ok(self.heading.turn(degrees)),
+        }
+    }
+
+    fn with_turns(turns: Turns) -> Self {
+        let origin = Position::default();
+        let east = Direction::East.into();
+        let waypoint = Position::new(10, 1);
+        Self::new(origin, east, waypoint, turns)
+    }
+}
+
+// A way of interpreting the navigation instructions.
+trait NavigationModel {
+    fn act(&self, ship: &mut Ship, instruction: &Instruction) -> Result<(), NavigationError>;
+
+    // Called once before the first instruction.
+    fn start(&self, _ship: &mut Ship) -> Result<(), NavigationError> {
+        Ok(())
+    }
+
+    // The waypoint relative to the ship, in compass terms, if the model steers by one.
+    fn waypoint(&self, _ship: &Ship) -> Option<Position> {
+        None
+    }
+}
+
+// Actions move the ship itself.
+struct Direct;
+
+impl NavigationModel for Direct {
+    fn act(&self, ship: &mut Ship, instruction: &Instruction) -> Result<(), NavigationError> {
         match instruction {
             Instruction::Direction(direction, amount) => {
-                self.position = self.position.move_towards(*direction, *amount)
+                ship.position = ship.position.move_towards(*direction, *amount)
             }
             Instruction::Forward(amount) => {
-                self.position = self.position.move_towards(self.direction, *amount)
+                ship.position = ship
+                    .position
+                    .move_along(ship.heading, *amount, ship.turns)?
             }
-            Instruction::Right(amount) => self.direction = self.direction.add_degrees(*amount),
-            Instruction::Left(amount) => self.direction = self.direction.add_degrees(-amount),
+            Instruction::Right(amount) => ship.heading = ship.turn(*amount)?,
+            Instruction::Left(amount) => ship.heading = ship.turn(-amount)?,
         }
+        Ok(())
     }
+}
+
+// Actions move the waypoint, except for going forward towards it.
+struct Waypoint;
+
+impl NavigationModel for Waypoint {
+    fn act(&self, ship: &mut Ship, instruction: &Instruction) -> Result<(), NavigationError> {
+        match instruction {
+            Instruction::Direction(direction, amount) => {
+                ship.waypoint = ship.waypoint.move_towards(*direction, *amount)
+            }
+            Instruction::Forward(amount) => {
+                ship.position = ship
+                    .position
+                    .step(ship.waypoint.east * amount, ship.waypoint.north * amount)
+            }
+            Instruction::Right(amount) => {
+                ship.waypoint = ship.waypoint.rotate(*amount, ship.turns)?
+            }
+            Instruction::Left(amount) => {
+                ship.waypoint = ship.waypoint.rotate(-amount, ship.turns)?
+            }
+        }
+        Ok(())
+    }
+
+    fn waypoint(&self, ship: &Ship) -> Option<Position> {
+        Some(ship.waypoint)
+    }
+}
 
-    fn act2(&mut self, instruction: &Instruction) {
+// The waypoint is fixed relative to the ship's bow, so it swings round whenever the ship turns.
+// Compass actions move the ship.  The ship's waypoint is kept as if the ship were heading north.
+struct RelativeWaypoint;
+
+impl NavigationModel for RelativeWaypoint {
+    fn act(&self, ship: &mut Ship, instruction: &Instruction) -> Result<(), NavigationError> {
         match instruction {
             Instruction::Direction(direction, amount) => {
-                self.waypoint = self.waypoint.move_towards(*direction, *amount)
+                ship.position = ship.position.move_towards(*direction, *amount)
             }
             Instruction::Forward(amount) => {
-                self.position = self
+                let waypoint = ship.waypoint.rotate(ship.heading.degrees, ship.turns)?;
+                ship.position = ship
                     .position
-                    .step(self.waypoint.east * amount, self.waypoint.north * amount)
+                    .step(waypoint.east * amount, waypoint.north * amount)
             }
-            Instruction::Right(amount) => self.waypoint = self.waypoint.rotate(*amount),
-            Instruction::Left(amount) => self.waypoint = self.waypoint.rotate(360 - amount),
+            Instruction::Right(amount) => ship.heading = ship.turn(*amount)?,
+            Instruction::Left(amount) => ship.heading = ship.turn(-amount)?,
         }
+        Ok(())
+    }
+
+    fn start(&self, ship: &mut Ship) -> Result<(), NavigationError> {
+        ship.waypoint = ship.waypoint.rotate(-ship.heading.degrees, ship.turns)?;
+        Ok(())
+    }
+
+    fn waypoint(&self, ship: &Ship) -> Option<Position> {
+        ship.waypoint.rotate(ship.heading.degrees, ship.turns).ok()
+    }
+}
+
+// Another model, with the ship carried along by a current after every action.
+struct Drift {
+    model: Box<dyn NavigationModel>,
+    current: Position,
+}
+
+impl NavigationModel for Drift {
+    fn act(&self, ship: &mut Ship, instruction: &Instruction) -> Result<(), NavigationError> {
+        self.model.act(ship, instruction)?;
+        ship.position = ship.position.step(self.current.east, self.current.north);
+        Ok(())
+    }
+
+    fn start(&self, ship: &mut Ship) -> Result<(), NavigationError> {
+        self.model.start(ship)
+    }
+
+    fn waypoint(&self, ship: &Ship) -> Option<Position> {
+        self.model.waypoint(ship)
+    }
+}
+
+// Settings that models can be built with.
+struct ModelOptions {
+    current: Position,
+}
+
+type ModelFactory = Box<dyn Fn(&ModelOptions) -> Box<dyn NavigationModel>>;
+
+// Navigation models by name.  New ones can be added with register.
+struct Models {
+    factories: BTreeMap<String, ModelFactory>,
+}
+
+impl Models {
+    fn register(
+        &mut self,
+        name: &str,
+        factory: impl Fn(&ModelOptions) -> Box<dyn NavigationModel> + 'static,
+    ) {
+        self.factories.insert(name.to_string(), Box::new(factory));
+    }
+
+    fn build(&self, name: &str, options: &ModelOptions) -> Option<Box<dyn NavigationModel>> {
+        self.factories.get(name).map(|factory| factory(options))
+    }
+
+    fn names(&self) -> impl Iterator<Item = &str> {
+        self.factories.keys().map(|name| name.as_str())
     }
 }
 
-impl Default for Ship {
+impl Default for Models {
     fn default() -> Self {
-        let origin = Position::default();
-        let east = Direction::East;
-        let waypoint = Position::new(10, 1);
-        Self::new(origin, east, waypoint)
+        let mut models = Self {
+            factories: BTreeMap::new(),
+        };
+        models.register("direct", |_| Box::new(Direct));
+        models.register("waypoint", |_| Box::new(Waypoint));
+        models.register("relative-waypoint", |_| Box::new(RelativeWaypoint));
+        models.register("drift", |options| {
+            Box::new(Drift {
+                model: Box::new(Direct),
+                current: options.current,
+            })
+        });
+        models.register("waypoint-drift", |options| {
+            Box::new(Drift {
+                model: Box::new(Waypoint),
+                current: options.current,
+            })
+        });
+        models
+    }
+}
+
+// Prints the track's statistics and saves it as name.svg in the directory, as asked.
+fn show_track(track: &Track, name: &str, show: &ShowTrack) {
+    if show.track_stats {
+        println!("Track for {}:", name);
+        track.print_stats();
     }
+    if let Some(directory) = &show.track_svg {
+        let path = directory.join(format!("{}.svg", name));
+        let saved =
+            std::fs::create_dir_all(directory).and_then(|()| std::fs::write(&path, track.to_svg()));
+        if let Err(err) = saved {
+            println!("Couldn't save {}: {}", path.display(), err);
+        }
+    }
+}
+
+// What to show of the day 12 ship's track.
+#[derive(StructOpt)]
+pub(crate) struct ShowTrack {
+    /// Print statistics about each day 12 track
+    #[structopt(long)]
+    track_stats: bool,
+
+    /// Save each day 12 track as an SVG file in this directory
+    #[structopt(long)]
+    track_svg: Option<PathBuf>,
+}
+
+// Extra navigation models to run the day 12 instructions through.
+#[derive(StructOpt)]
+pub(crate) struct ExtraModels {
+    /// Also navigate day 12 with these models
+    #[structopt(long = "model", number_of_values = 1)]
+    models: Vec<String>,
+
+    /// The current that drift models carry the day 12 ship along by, east then north
+    #[structopt(
+        long,
+        use_delimiter = true,
+        allow_hyphen_values = true,
+        default_value = "0,0"
+    )]
+    current: Vec<isize>,
 }
 
-pub(crate) fn day12() {
+fn report(
+    name: &str,
+    model: &dyn NavigationModel,
+    instructions: &[Instruction],
+    turns: Turns,
+) -> Ship {
+    let mut ship = Ship::with_turns(turns);
+    match ship.navigate(model, instructions) {
+        Ok(()) => println!("{} answer is {}", name, ship.position.manhattan()),
+        Err(err) => println!("{} has no answer: {}", name, err),
+    }
+    ship
+}
+
+pub(crate) fn day12(rounded_turns: bool, show: &ShowTrack, extra: &ExtraModels) {
     let input = std::fs::read_to_string("data/day12.txt").unwrap();
     let instructions: Vec<Instruction> = input.lines().map(|line| line.parse().unwrap()).collect();
+    let turns = if rounded_turns {
+        Turns::Rounded
+    } else {
+        Turns::Exact
+    };
 
-    let mut ship = Ship::default();
-    for instruction in &instructions {
-        ship.act(instruction);
-    }
-    println!("Part one answer is {}", ship.position.manhattan());
+    let ship = report("Part one", &Direct, &instructions, turns);
+    show_track(&ship.track, "part-one", show);
 
-    let mut ship = Ship::default();
-    for instruction in &instructions {
-        ship.act2(instruction);
+    let ship = report("Part two", &Waypoint, &instructions, turns);
+    show_track(&ship.track, "part-two", show);
+
+    let models = Models::default();
+    let options = ModelOptions {
+        current: match extra.current[..] {
+            [east, north] => Position::new(east, north),
+            _ => panic!("The current needs an east and a north part"),
+        },
+    };
+    for name in &extra.models {
+        match models.build(name, &options) {
+            Some(model) => {
+                let ship = report(name, model.as_ref(), &instructions, turns);
+                show_track(&ship.track, name, show);
+            }
+            None => {
+                let names: Vec<&str> = models.names().collect();
+                println!("No model {}, try one of {}", name, names.join(", "));
+            }
+        }
     }
-    println!("Part two answer is {}", ship.position.manhattan());
 }
@@ -1,7 +1,14 @@
-// Utilities recovered from advent of code 2019...
-fn modular_multiplication(modulus: u64, x: u64, y: u64) -> u64 {
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fmt;
+use std::fmt::Display;
+use std::str::FromStr;
+use structopt::StructOpt;
+
+// Utilities recovered from advent of code 2019, widened so that products of bus IDs fit.
+fn modular_multiplication(modulus: u128, x: u128, y: u128) -> u128 {
     let mut result = 0;
-    let mut a = x;
+    let mut a = x % modulus;
     let mut b = y;
     while b != 0 {
         if (b % 2) == 1 {
@@ -13,7 +20,8 @@ fn modular_multiplication(modulus: u64, x: u64, y: u64) -> u64 {
     result
 }
 
-fn modular_inverse(modulus: u64, n: u64) -> u64 {
+// There's only an inverse when n and the modulus have no common factors.
+fn modular_inverse(modulus: u128, n: u128) -> Option<u128> {
     let mut a = modulus;
     let mut b = n % modulus;
     let mut x0 = 0;
@@ -27,48 +35,349 @@ fn modular_inverse(modulus: u64, n: u64) -> u64 {
         b = remainder;
 
         let temp = x1;
-        x1 = (x0 + modular_multiplication(modulus, modulus - quotient, x1)) % modulus;
+        x1 = (x0 + modular_multiplication(modulus, modulus - quotient % modulus, x1)) % modulus;
         x0 = temp;
     }
-    assert_eq!(a, 1);
 
-    x0
+    if a == 1 {
+        Some(x0)
+    } else {
+        None
+    }
+}
+
+fn gcd(a: u128, b: u128) -> u128 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+// When the buses leave in part two's pattern.
+#[derive(Debug)]
+enum Alignment {
+    // The first time, and how often it comes round again.
+    At { time: u128, period: u128 },
+    NoSuchTimestamp,
+
+    // The answer doesn't fit in 128 bits.
+    Overflow,
+}
+
+impl Display for Alignment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::At { time, period } => write!(f, "at {}, repeating every {}", time, period),
+            Self::NoSuchTimestamp => write!(f, "no such timestamp"),
+            Self::Overflow => write!(f, "the timestamp is too large"),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum ParseTimetableError {
+    MissingLine,
+    BadTime(String),
+    BadBus(String),
+    NoBuses,
+}
+
+impl Display for ParseTimetableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingLine => write!(f, "expected a time and a list of buses"),
+            Self::BadTime(time) => write!(f, "bad time {}", time),
+            Self::BadBus(bus) => write!(f, "bad bus {}", bus),
+            Self::NoBuses => write!(f, "no buses in service"),
+        }
+    }
+}
+
+// When we reach the bus stop, and each bus in service with its position in the list.
+struct Timetable {
+    now: u64,
+    buses: Vec<(u64, u64)>,
+}
+
+impl FromStr for Timetable {
+    type Err = ParseTimetableError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines();
+        let (now, list) = match (lines.next(), lines.next()) {
+            (Some(now), Some(list)) => (now.trim(), list.trim()),
+            _ => return Err(ParseTimetableError::MissingLine),
+        };
+        let now = now
+            .parse()
+            .map_err(|_| ParseTimetableError::BadTime(now.to_string()))?;
+
+        let mut buses = vec![];
+        for (offset, bus) in list.split(',').enumerate() {
+            if bus == "x" {
+                continue;
+            }
+            match bus.parse() {
+                Ok(id) if id > 0 => buses.push((offset as u64, id)),
+                _ => return Err(ParseTimetableError::BadBus(bus.to_string())),
+            }
+        }
+        if buses.is_empty() {
+            return Err(ParseTimetableError::NoBuses);
+        }
+        Ok(Self { now, buses })
+    }
+}
+
+// The first time at or after t that a bus leaves.
+fn next_departure(id: u64, t: u64) -> u64 {
+    t.div_ceil(id) * id
+}
+
+impl Timetable {
+    fn ids(&self) -> impl Iterator<Item = u64> + '_ {
+        self.buses.iter().map(|&(_offset, id)| id)
+    }
+
+    // The first k departures at or after t, in time order, as (time, bus) pairs.  Buses that
+    // leave together are ordered by ID.
+    fn departures(&self, t: u64, k: usize) -> Vec<(u64, u64)> {
+        let mut queue: BinaryHeap<Reverse<(u64, u64)>> = self
+            .ids()
+            .map(|id| Reverse((next_departure(id, t), id)))
+            .collect();
+        let mut departures = vec![];
+        while departures.len() < k {
+            let Reverse((time, id)) = queue.pop().unwrap();
+            departures.push((time, id));
+            queue.push(Reverse((time + id, id)));
+        }
+        departures
+    }
+
+    // The first time at or after t from which every one of the buses leaves within the next
+    // window minutes.  Each choice of how far into the window each bus leaves is a set of
+    // congruences, so this tries every choice and keeps the earliest solution.  Choices are
+    // abandoned as soon as the buses chosen so far can never line up.
+    fn first_within_window(
+        &self,
+        ids: &[u64],
+        window: u64,
+        t: u64,
+    ) -> Result<Option<u128>, WindowError> {
+        ids.iter()
+            .try_fold(1u128, |choices, _id| {
+                choices.checked_mul(u128::from(window) + 1)
+            })
+            .filter(|&choices| choices <= MAX_WINDOW_CHOICES)
+            .ok_or(WindowError::TooManyChoices)?;
+        let mut earliest = None;
+        search_window(ids, window, t.into(), (0, 1), &mut earliest)?;
+        Ok(earliest)
+    }
+
+    // Part two's answer, and how often the same pattern comes round again.
+    fn alignment(&self) -> Alignment {
+        let congruences: Vec<(u128, u128)> = self
+            .buses
+            .iter()
+            .map(|&(t, m)| {
+                let (t, m) = (t as u128, m as u128);
+                ((m - t % m) % m, m)
+            })
+            .collect();
+
+        // Accumulator is the solution so far, and combined modulus so far.
+        //
+        // If we didn't have all the machinery lying around from last year, this simple thing would
+        // work - and the numbers that we're working with are small enough that it's not even slow.
+        //
+        //  let (solution, _) = congruences.iter().fold((0, 1u64), |(soln, bigm), (a, m)| {
+        //      let soln = (soln..).step_by(bigm as usize).find(|n| n % m == *a).unwrap();
+        //      (soln, bigm * m)
+        //  });
+        //
+        // IDs needn't be coprime.  Adding multiples of bigm to the solution only reaches the
+        // remainders mod m that differ from it by a multiple of their common factor g, and the
+        // combined modulus is their lowest common multiple.
+        congruences
+            .iter()
+            .try_fold((0, 1), |(soln, bigm), &(a, m)| {
+                match combine(soln, bigm, a, m) {
+                    Alignment::At { time, period } => Ok((time, period)),
+                    alignment => Err(alignment),
+                }
+            })
+            .map_or_else(
+                |alignment| alignment,
+                |(time, period)| Alignment::At { time, period },
+            )
+    }
+
+    // The first time at or after t when the buses leave in part two's pattern.
+    fn next_alignment(&self, t: u128) -> Alignment {
+        match self.alignment() {
+            Alignment::At { time, period } => match at_or_after(time, period, t) {
+                Some(time) => Alignment::At { time, period },
+                None => Alignment::Overflow,
+            },
+            alignment => alignment,
+        }
+    }
+}
+
+// Adds x = a (mod m) to the solution so far, x = soln (mod bigm).
+fn combine(soln: u128, bigm: u128, a: u128, m: u128) -> Alignment {
+    let g = gcd(bigm, m);
+    let need = (m + a - (soln % m)) % m;
+    if !need.is_multiple_of(g) {
+        return Alignment::NoSuchTimestamp;
+    }
+    let step = m / g;
+    let bigmi = modular_inverse(step, bigm / g).unwrap();
+    let k = modular_multiplication(step, need / g, bigmi);
+    match bigm.checked_mul(step) {
+        Some(lcm) => Alignment::At {
+            time: soln + bigm * k,
+            period: lcm,
+        },
+        None => Alignment::Overflow,
+    }
+}
+
+// The first time at or after t that's a whole number of periods after time.
+fn at_or_after(time: u128, period: u128, t: u128) -> Option<u128> {
+    if t <= time {
+        return Some(time);
+    }
+    let periods = (t - time).div_ceil(period);
+    periods.checked_mul(period)?.checked_add(time)
+}
+
+// Trying every way of spreading the buses over the window soon gets out of hand.
+const MAX_WINDOW_CHOICES: u128 = 1 << 20;
+
+#[derive(Debug)]
+enum WindowError {
+    TooManyChoices,
+    Overflow,
+}
+
+impl Display for WindowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooManyChoices => write!(
+                f,
+                "there are more than {} ways to place the buses in the window, so gave up",
+                MAX_WINDOW_CHOICES
+            ),
+            Self::Overflow => write!(f, "the timestamp is too large"),
+        }
+    }
 }
 
-pub(crate) fn day13() {
+// Chooses when in the window the first of the ids leaves, then moves on to the rest.  Once every
+// bus has a place, the earliest time at or after t that fits is a candidate.
+fn search_window(
+    ids: &[u64],
+    window: u64,
+    t: u128,
+    (soln, bigm): (u128, u128),
+    earliest: &mut Option<u128>,
+) -> Result<(), WindowError> {
+    let (id, rest) = match ids.split_first() {
+        Some((&id, rest)) => (u128::from(id), rest),
+        None => {
+            let time = at_or_after(soln, bigm, t).ok_or(WindowError::Overflow)?;
+            if earliest.is_none_or(|earliest| time < earliest) {
+                *earliest = Some(time);
+            }
+            return Ok(());
+        }
+    };
+    for wait in 0..=u128::from(window) {
+        match combine(soln, bigm, (id - wait % id) % id, id) {
+            Alignment::At { time, period } => {
+                search_window(rest, window, t, (time, period), earliest)?
+            }
+            Alignment::NoSuchTimestamp => {}
+            Alignment::Overflow => return Err(WindowError::Overflow),
+        }
+    }
+    Ok(())
+}
+
+// Questions to ask of the day 13 timetable.
+#[derive(StructOpt)]
+pub(crate) struct TimetableQueries {
+    /// List this many day 13 departures
+    #[structopt(long)]
+    departures: Option<usize>,
+
+    /// Find when these day 13 buses all leave within the window
+    #[structopt(long, use_delimiter = true)]
+    buses: Vec<u64>,
+
+    /// How many minutes the day 13 buses have to leave within
+    #[structopt(long, default_value = "0")]
+    window: u64,
+
+    /// Ask day 13 questions about times from this one, rather than when we reach the stop
+    #[structopt(long)]
+    after: Option<u64>,
+}
+
+pub(crate) fn day13(queries: &TimetableQueries) {
     let input = std::fs::read_to_string("data/day13.txt").unwrap();
-    let mut lines = input.lines();
-    let now: u64 = lines.next().unwrap().parse().unwrap();
-    let timetable = lines.next().unwrap();
-    let ids: Vec<u64> = timetable
-        .split(',')
-        .filter_map(|line| line.parse().ok())
-        .collect();
-
-    let (wait, id) = ids.iter().map(|id| (id - (now % id), id)).min().unwrap();
-    println!("Part one answer is {}", wait * id);
-
-    let congruences: Vec<(u64, u64)> = timetable
-        .split(',')
-        .enumerate()
-        .filter_map(|(t, id)| id.parse().ok().map(|m| ((m - t as u64 % m) % m, m)))
-        .collect();
-
-    // Accumulator is the solution so far, and combined modulus so far.
-    //
-    // If we didn't have all the machinery lying around from last year, this simple thing would
-    // work - and the numbers that we're working with are small enough that it's not even slow.
-    //
-    //  let (solution, _) = congruences.iter().fold((0, 1u64), |(soln, bigm), (a, m)| {
-    //      let soln = (soln..).step_by(bigm as usize).find(|n| n % m == *a).unwrap();
-    //      (soln, bigm * m)
-    //  });
-    let (solution, _) = congruences.iter().fold((0, 1), |(soln, bigm), (a, m)| {
-        let bigmi = modular_inverse(*m, bigm);
-        let need = (m + a - (soln % m)) % m;
-        let soln = soln + need * bigm * bigmi;
-        let bigm = bigm * m;
-        (soln % bigm, bigm)
-    });
-    println!("Part two answer is {}", solution);
+    let timetable: Timetable = input.parse().unwrap();
+    let now = timetable.now;
+
+    let (time, id) = timetable.departures(now, 1)[0];
+    println!("Part one answer is {}", (time - now) * id);
+
+    match timetable.alignment() {
+        Alignment::At { time, period: _ } => println!("Part two answer is {}", time),
+        alignment => println!("Part two has no answer: {}", alignment),
+    }
+
+    let after = queries.after.unwrap_or(now);
+    if let Some(k) = queries.departures {
+        for (time, id) in timetable.departures(after, k) {
+            println!("Bus {} leaves at {}", id, time);
+        }
+    }
+
+    if !queries.buses.is_empty() {
+        let unknown: Vec<String> = queries
+            .buses
+            .iter()
+            .filter(|id| !timetable.ids().any(|known| known == **id))
+            .map(|id| id.to_string())
+            .collect();
+        if !unknown.is_empty() {
+            println!("Not in the timetable: {}", unknown.join(", "));
+        } else {
+            match timetable.first_within_window(&queries.buses, queries.window, after) {
+                Ok(Some(time)) => println!(
+                    "The buses all leave within {} minutes of {}",
+                    queries.window, time
+                ),
+                Ok(None) => println!(
+                    "The buses never all leave within {} minutes",
+                    queries.window
+                ),
+                Err(err) => println!("Couldn't fit the buses in the window: {}", err),
+            }
+        }
+    }
+
+    if let Some(t) = queries.after {
+        match timetable.next_alignment(t.into()) {
+            Alignment::At { time, period: _ } => {
+                println!("The pattern next comes round at {}", time)
+            }
+            alignment => println!("The pattern never comes round: {}", alignment),
+        }
+    }
 }
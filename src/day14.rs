@@ -1,125 +1,345 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fmt::Display;
 use std::str::FromStr;
 
+// Addresses and values are 36 bits wide.
+const WIDTH: usize = 36;
+const LIMIT: u64 = 1 << WIDTH;
+
+#[derive(Copy, Clone)]
+struct Mask {
+    // Bits that are 1 in the mask.
+    ones: u64,
+
+    // Bits that are 1 or X in the mask.
+    zeros: u64,
+}
+
+impl Mask {
+    // Bits that are X in the mask.
+    fn floating(&self) -> u64 {
+        self.zeros & !self.ones
+    }
+}
+
 enum Instruction {
-    Mask(u64, u64, String),
+    Mask(Mask),
     Set(u64, u64),
 }
 
 #[derive(Debug)]
-struct ParseInstructionError;
+enum ParseInstructionError {
+    MaskWidth(usize),
+    MaskBit(char),
+    Syntax,
+    Number(String),
+    TooWide(u64),
+}
 
-impl From<std::num::ParseIntError> for ParseInstructionError {
-    fn from(_err: std::num::ParseIntError) -> Self {
-        ParseInstructionError
+impl Display for ParseInstructionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MaskWidth(width) => {
+                write!(f, "mask has {} bits instead of {}", width, WIDTH)
+            }
+            Self::MaskBit(c) => write!(f, "mask bit {:?} isn't 0, 1 or X", c),
+            Self::Syntax => write!(f, "expected mask = ... or mem[address] = value"),
+            Self::Number(number) => write!(f, "bad number {:?}", number),
+            Self::TooWide(number) => write!(f, "{} doesn't fit in {} bits", number, WIDTH),
+        }
     }
 }
 
+fn parse_number(s: &str) -> Result<u64, ParseInstructionError> {
+    let number: u64 = s
+        .parse()
+        .map_err(|_| ParseInstructionError::Number(s.to_string()))?;
+    if number >= LIMIT {
+        return Err(ParseInstructionError::TooWide(number));
+    }
+    Ok(number)
+}
+
 impl FromStr for Instruction {
     type Err = ParseInstructionError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let instruction = if let Some(mask) = s.strip_prefix("mask = ") {
-            let onestring = mask.replace("X", "0");
-            let ones = u64::from_str_radix(&onestring, 2)?;
-
-            let zerostring = mask.replace("X", "1");
-            let zeros = u64::from_str_radix(&zerostring, 2)?;
-
-            let floating = mask.replace("1", "0");
-            Instruction::Mask(ones, zeros, floating)
+            let width = mask.chars().count();
+            if width != WIDTH {
+                return Err(ParseInstructionError::MaskWidth(width));
+            }
+            let mut ones = 0;
+            let mut zeros = 0;
+            for c in mask.chars() {
+                let (one, zero) = match c {
+                    '0' => (0, 0),
+                    '1' => (1, 1),
+                    'X' => (0, 1),
+                    _ => return Err(ParseInstructionError::MaskBit(c)),
+                };
+                ones = ones << 1 | one;
+                zeros = zeros << 1 | zero;
+            }
+            Instruction::Mask(Mask { ones, zeros })
         } else {
-            let mut words = s[4..].split("] = ");
-            let memory: u64 = words.next().ok_or(ParseInstructionError)?.parse()?;
-            let value: u64 = words.next().ok_or(ParseInstructionError)?.parse()?;
+            let set = s
+                .strip_prefix("mem[")
+                .and_then(|rest| rest.split_once("] = "))
+                .ok_or(ParseInstructionError::Syntax)?;
+            let memory = parse_number(set.0)?;
+            let value = parse_number(set.1)?;
             Instruction::Set(memory, value)
         };
         Ok(instruction)
     }
 }
 
-#[derive(Default)]
-struct Computer {
-    memory: HashMap<u64, u64>,
-    ones_mask: u64,
-    zeros_mask: u64,
-    floating: String,
+#[derive(Debug)]
+struct ParseError {
+    line: usize,
+    problem: ParseInstructionError,
 }
 
-fn scatter(xs: &str, value: u64) -> Option<(u64, u64)> {
-    let indexes: Vec<_> = xs
-        .chars()
-        .rev()
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.problem)
+    }
+}
+
+// Each instruction along with its line number.
+fn parse_program(input: &str) -> Result<Vec<(usize, Instruction)>, ParseError> {
+    input
+        .lines()
         .enumerate()
-        .filter(|(_n, c)| c == &'X')
-        .map(|(n, _c)| n)
-        .collect();
-    let count = indexes.len();
+        .map(|(index, line)| {
+            let line_number = index + 1;
+            line.parse()
+                .map(|instruction| (line_number, instruction))
+                .map_err(|problem| ParseError {
+                    line: line_number,
+                    problem,
+                })
+        })
+        .collect()
+}
+
+// A set of addresses: the floating bits can be anything, the others are as in fixed.
+#[derive(Copy, Clone)]
+struct Pattern {
+    fixed: u64,
+    floating: u64,
+}
+
+impl Pattern {
+    fn new(fixed: u64, floating: u64) -> Self {
+        Self {
+            fixed: fixed & !floating,
+            floating,
+        }
+    }
+
+    fn len(&self) -> u64 {
+        1 << self.floating.count_ones()
+    }
+
+    fn single(address: u64) -> Self {
+        Self::new(address, 0)
+    }
 
-    if value >= 1 << count {
-        return None;
+    fn overlaps(&self, other: &Self) -> bool {
+        let both_fixed = !self.floating & !other.floating;
+        (self.fixed ^ other.fixed) & both_fixed == 0
     }
 
-    let mut ones = 0;
-    let mut zeros = 0;
-    for (old_bit, &new_bit) in indexes.iter().enumerate() {
-        let onezero = if value & (1 << old_bit) != 0 { 1 } else { 0 };
-        ones |= onezero << new_bit;
-        zeros |= (1 - onezero) << new_bit;
+    // The addresses in this pattern but not the other, as patterns that don't overlap.  Each bit
+    // that floats here but is fixed in the other splits off the addresses that have it the other
+    // way, until what's left is inside the other pattern.
+    fn subtract(&self, other: &Self) -> Vec<Self> {
+        if !self.overlaps(other) {
+            return vec![*self];
+        }
+        let mut pieces = vec![];
+        let mut rest = *self;
+        let mut split = self.floating & !other.floating;
+        while split != 0 {
+            let bit = split & split.wrapping_neg();
+            split &= !bit;
+            let floating = rest.floating & !bit;
+            pieces.push(Self::new(rest.fixed | (!other.fixed & bit), floating));
+            rest = Self::new(rest.fixed | (other.fixed & bit), floating);
+        }
+        pieces
     }
-    Some((ones, zeros))
 }
 
-impl Computer {
-    fn step(&mut self, instruction: &Instruction) {
-        match instruction {
-            Instruction::Mask(ones, zeros, _) => {
-                self.ones_mask = *ones;
-                self.zeros_mask = *zeros;
-            }
-            Instruction::Set(memory, value) => {
-                let write = (value | self.ones_mask) & self.zeros_mask;
-                self.memory.insert(*memory, write);
-            }
+// A single address in decimal, otherwise the bits with X for floating ones.
+impl Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.floating == 0 {
+            return write!(f, "{}", self.fixed);
         }
+        for bit in (0..WIDTH).rev() {
+            let c = match (self.floating >> bit & 1, self.fixed >> bit & 1) {
+                (1, _) => 'X',
+                (_, 1) => '1',
+                _ => '0',
+            };
+            write!(f, "{}", c)?;
+        }
+        Ok(())
     }
+}
 
-    fn step2(&mut self, instruction: &Instruction) {
-        match instruction {
-            Instruction::Mask(ones, _, floating) => {
-                self.floating = floating.clone();
-                self.ones_mask = *ones;
-            }
-            Instruction::Set(memory, value) => {
-                for n in 0.. {
-                    if let Some((ones, zeros)) = scatter(&self.floating, n) {
-                        let address = (*memory | self.ones_mask | ones) & !zeros;
-                        self.memory.insert(address, *value);
-                    } else {
-                        break;
-                    }
+// Memory for the version 2 decoder.  Writes are kept as patterns of addresses rather than every
+// address, so masks with lots of floating bits stay cheap.  A write takes its addresses out of
+// every earlier pattern, so the patterns never overlap.  Each write remembers the line of the
+// instruction that made it.
+#[derive(Default)]
+struct FloatingMemory {
+    writes: Vec<(Pattern, u64, usize)>,
+}
+
+impl FloatingMemory {
+    fn write(&mut self, addresses: Pattern, value: u64, line: usize) {
+        let mut writes = Vec::with_capacity(self.writes.len() + 1);
+        for (pattern, old, old_line) in self.writes.drain(..) {
+            let pieces = pattern.subtract(&addresses).into_iter();
+            writes.extend(pieces.map(|p| (p, old, old_line)));
+        }
+        writes.push((addresses, value, line));
+        self.writes = writes;
+    }
+}
+
+// A version of the decoder chip, which decides what a write does with the mask.
+trait Decoder {
+    // The mask in force before the program sets one.
+    const UNMASKED: Mask;
+
+    fn write(&mut self, mask: &Mask, address: u64, value: u64, line: usize);
+
+    // Every address and value fits in 36 bits, so even a full memory can't overflow this.
+    fn sum(&self) -> u128;
+
+    // What's in memory, ordered by address, with the line of the instruction that wrote it.
+    fn dump(&self) -> Vec<(Pattern, u64, usize)>;
+}
+
+// The mask changes the value.
+#[derive(Default)]
+struct Version1 {
+    memory: BTreeMap<u64, (u64, usize)>,
+}
+
+impl Decoder for Version1 {
+    // Every value bit is cleared, so writes store 0.
+    const UNMASKED: Mask = Mask { ones: 0, zeros: 0 };
+
+    fn write(&mut self, mask: &Mask, address: u64, value: u64, line: usize) {
+        let value = (value | mask.ones) & mask.zeros;
+        self.memory.insert(address, (value, line));
+    }
+
+    fn sum(&self) -> u128 {
+        self.memory
+            .values()
+            .map(|&(value, _line)| u128::from(value))
+            .sum()
+    }
+
+    fn dump(&self) -> Vec<(Pattern, u64, usize)> {
+        self.memory
+            .iter()
+            .map(|(&address, &(value, line))| (Pattern::single(address), value, line))
+            .collect()
+    }
+}
+
+// The mask changes the address, and floating bits write to every address they could make.
+#[derive(Default)]
+struct Version2 {
+    memory: FloatingMemory,
+}
+
+impl Decoder for Version2 {
+    // No bits are set or floating, so writes go to the address as given.
+    const UNMASKED: Mask = Mask { ones: 0, zeros: 0 };
+
+    fn write(&mut self, mask: &Mask, address: u64, value: u64, line: usize) {
+        let addresses = Pattern::new(address | mask.ones, mask.floating());
+        self.memory.write(addresses, value, line);
+    }
+
+    fn sum(&self) -> u128 {
+        self.memory
+            .writes
+            .iter()
+            .map(|(pattern, value, _line)| u128::from(pattern.len()) * u128::from(*value))
+            .sum()
+    }
+
+    fn dump(&self) -> Vec<(Pattern, u64, usize)> {
+        let mut dump = self.memory.writes.clone();
+        dump.sort_by_key(|(pattern, _value, _line)| (pattern.fixed, pattern.floating));
+        dump
+    }
+}
+
+struct Computer<D: Decoder> {
+    decoder: D,
+    mask: Mask,
+}
+
+impl<D: Decoder + Default> Default for Computer<D> {
+    fn default() -> Self {
+        Self {
+            decoder: D::default(),
+            mask: D::UNMASKED,
+        }
+    }
+}
+
+impl<D: Decoder> Computer<D> {
+    fn run(&mut self, program: &[(usize, Instruction)]) {
+        for (line, instruction) in program {
+            match instruction {
+                Instruction::Mask(mask) => self.mask = *mask,
+                Instruction::Set(address, value) => {
+                    self.decoder.write(&self.mask, *address, *value, *line)
                 }
             }
         }
     }
 
-    fn sum(&self) -> u64 {
-        self.memory.values().sum()
+    fn print_dump(&self) {
+        for (addresses, value, line) in self.decoder.dump() {
+            println!("  mem[{}] = {} (line {})", addresses, value, line);
+        }
     }
 }
 
-pub(crate) fn day14() {
+pub(crate) fn day14(dump: bool) {
     let input = std::fs::read_to_string("data/day14.txt").unwrap();
-    let instructions: Vec<Instruction> = input.lines().map(|line| line.parse().unwrap()).collect();
+    let program = match parse_program(&input) {
+        Ok(program) => program,
+        Err(err) => {
+            println!("Bad program: {}", err);
+            return;
+        }
+    };
 
-    let mut computer = Computer::default();
-    for instruction in &instructions {
-        computer.step(instruction);
+    let mut computer = Computer::<Version1>::default();
+    computer.run(&program);
+    println!("Part one answer is {}", computer.decoder.sum());
+    if dump {
+        computer.print_dump();
     }
-    println!("Part one answer is {}", computer.sum());
 
-    let mut computer = Computer::default();
-    for instruction in &instructions {
-        computer.step2(instruction);
+    let mut computer = Computer::<Version2>::default();
+    computer.run(&program);
+    println!("Part two answer is {}", computer.decoder.sum());
+    if dump {
+        computer.print_dump();
     }
-    println!("Part two answer is {}", computer.sum());
 }
@@ -9,7 +9,7 @@ impl Game {
         Self { seed }
     }
 
-    fn iter(&self) -> GameIter {
+    fn iter(&self) -> GameIter<'_> {
         GameIter::new(self.seed.iter())
     }
 }
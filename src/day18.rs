@@ -105,7 +105,7 @@ pub(crate) fn day18() {
     let total: u64 = problems
         .iter()
         .map(|problem| {
-            let parsed = shunting_yard(&precedence, &problem);
+            let parsed = shunting_yard(&precedence, problem);
             eval_rpn(&parsed)
         })
         .sum();
@@ -115,7 +115,7 @@ pub(crate) fn day18() {
     let total: u64 = problems
         .iter()
         .map(|problem| {
-            let parsed = shunting_yard(&precedence, &problem);
+            let parsed = shunting_yard(&precedence, problem);
             eval_rpn(&parsed)
         })
         .sum();
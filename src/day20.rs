@@ -270,8 +270,7 @@ pub(crate) fn day20() {
     let tiles: Vec<Tile> = input
         .split("\n\n")
         .map(|block| block.parse::<Tile>().unwrap())
-        .map(|tile| tile.variants())
-        .flatten()
+        .flat_map(|tile| tile.variants())
         .collect();
 
     let mut grid = Grid::default();
@@ -62,8 +62,7 @@ pub(crate) fn day21() {
 
     let safe_appearances = recipes
         .iter()
-        .map(|recipe| recipe.ingredients.iter())
-        .flatten()
+        .flat_map(|recipe| recipe.ingredients.iter())
         .filter(|ingredient| !danger_ingredients.contains(ingredient.as_str()))
         .count();
     println!("Part one answer is {}", safe_appearances);
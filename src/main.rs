@@ -1,7 +1,6 @@
 #[macro_use]
-extern crate lazy_static;
-#[macro_use]
 extern crate nom;
+use std::path::PathBuf;
 use structopt::StructOpt;
 mod day01;
 mod day02;
@@ -33,25 +32,126 @@ mod utils;
 #[derive(StructOpt)]
 struct Cli {
     day: u8,
+
+    #[structopt(subcommand)]
+    command: Option<day08::Command>,
+
+    /// Passport validation schema for day 4, as TOML or JSON
+    #[structopt(long, parse(from_os_str))]
+    schema: Option<PathBuf>,
+
+    /// Explain why each day 4 passport is invalid, as text or json
+    #[structopt(long)]
+    report: Option<day04::ReportFormat>,
+
+    /// Show the day 5 seat map, with our seat and boarding pass
+    #[structopt(long)]
+    seat_map: bool,
+
+    /// How many rows the day 5 plane has, a power of two
+    #[structopt(long)]
+    plane_rows: Option<u32>,
+
+    /// How many columns the day 5 plane has, a power of two
+    #[structopt(long)]
+    plane_columns: Option<u32>,
+
+    /// Show answer statistics for each day 6 group
+    #[structopt(long)]
+    group_stats: bool,
+
+    /// Day 7 bag to query, defaults to "shiny gold"
+    #[structopt(long)]
+    bag: Option<String>,
+
+    /// Show how the day 7 bag eventually contains this one
+    #[structopt(long)]
+    path_to: Option<String>,
+
+    /// Write the day 7 bag rules to this file as a Graphviz graph
+    #[structopt(long, parse(from_os_str))]
+    dot: Option<PathBuf>,
+
+    /// Report every fix for the day 8 boot code, and any unreachable code
+    #[structopt(long)]
+    analyse: bool,
+
+    /// Log every day 8 instruction as it is executed
+    #[structopt(long)]
+    trace: bool,
+
+    /// How many numbers precede each checked day 9 number, defaults to 25
+    #[structopt(long)]
+    preamble: Option<usize>,
+
+    /// Check day 9 numbers read from stdin, reporting every invalid one
+    #[structopt(long)]
+    stdin: bool,
+
+    /// Shortest run of numbers for the day 9 weakness, defaults to 2
+    #[structopt(long)]
+    min_run: Option<usize>,
+
+    /// Allowed day 10 joltage differences, defaults to 1,2,3
+    #[structopt(long, use_delimiter = true)]
+    gaps: Vec<usize>,
+
+    /// How far the day 10 device is above the highest adapter, defaults to 3
+    #[structopt(long)]
+    device_offset: Option<usize>,
+
+    #[structopt(flatten)]
+    arrangements: day10::ShowArrangements,
+
+    #[structopt(flatten)]
+    seating: day11::CustomSeating,
+
+    #[structopt(flatten)]
+    frames: day11::Frames,
+
+    /// Allow day 12 turns by any number of degrees, rounding positions to whole units
+    #[structopt(long)]
+    rounded_turns: bool,
+
+    #[structopt(flatten)]
+    track: day12::ShowTrack,
+
+    #[structopt(flatten)]
+    models: day12::ExtraModels,
+
+    #[structopt(flatten)]
+    timetable: day13::TimetableQueries,
+
+    /// Print day 14 memory after each program, with the line that last wrote each address
+    #[structopt(long)]
+    dump: bool,
 }
 
 fn main() {
     let args = Cli::from_args();
+    if args.command.is_some() && args.day != 8 {
+        println!("Only day 8 has subcommands");
+        return;
+    }
     match args.day {
         1 => day01::day01(),
         2 => day02::day02(),
         3 => day03::day03(),
-        4 => day04::day04(),
-        5 => day05::day05(),
-        6 => day06::day06(),
-        7 => day07::day07(),
-        8 => day08::day08(),
-        9 => day09::day09(),
-        10 => day10::day10(),
-        11 => day11::day11(),
-        12 => day12::day12(),
-        13 => day13::day13(),
-        14 => day14::day14(),
+        4 => day04::day04(args.schema.as_deref(), args.report),
+        5 => day05::day05(args.seat_map, args.plane_rows, args.plane_columns),
+        6 => day06::day06(args.group_stats),
+        7 => day07::day07(
+            args.bag.as_deref(),
+            args.path_to.as_deref(),
+            args.dot.as_deref(),
+        ),
+        8 => day08::day08(args.command.as_ref(), args.analyse, args.trace),
+        9 => day09::day09(args.preamble, args.stdin, args.min_run),
+        10 => day10::day10(&args.gaps, args.device_offset, &args.arrangements),
+        11 => day11::day11(&args.seating, &args.frames),
+        12 => day12::day12(args.rounded_turns, &args.track, &args.models),
+        13 => day13::day13(&args.timetable),
+        14 => day14::day14(args.dump),
         15 => day15::day15(),
         16 => day16::day16(),
         17 => day17::day17(),